@@ -0,0 +1,134 @@
+//! Server configuration given by command line options
+use std::path::PathBuf;
+
+use crate::http::compression::CompressionConfig;
+
+/// Options of the server
+/// `--directory <dir>`: root directory of the `/files` routes
+/// `--create-dirs`: create missing parent directories when uploading a file
+/// `--no-listing`: answer 404 for directories instead of listing them
+/// `--weak-etags`: send weak entity tags for files
+/// `--mime-types <file>`: `mime.types` file overriding the built-in media types
+/// `--sniff`: guess the media type of files without a known extension from their content
+/// `--no-compression`: never compress responses
+/// `--compression-level <0-9>`: compression level of responses
+/// `--compression-min-size <bytes>`: smallest body that is compressed
+/// `--compression-types <types>`: comma-separated media types that are compressed, e.g. `text/*,application/json`
+#[derive(Debug, Default)]
+pub struct Config {
+    pub directory: Option<PathBuf>,
+    pub create_dirs: bool,
+    pub no_listing: bool,
+    pub weak_etags: bool,
+    pub mime_types: Option<PathBuf>,
+    pub sniff: bool,
+    pub no_compression: bool,
+    pub compression: CompressionConfig,
+}
+
+impl Config {
+    /// Parse the options, ignoring unknown ones
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        let mut config = Config::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--directory" => config.directory = args.next().map(PathBuf::from),
+                "--create-dirs" => config.create_dirs = true,
+                "--no-listing" => config.no_listing = true,
+                "--weak-etags" => config.weak_etags = true,
+                "--mime-types" => config.mime_types = args.next().map(PathBuf::from),
+                "--sniff" => config.sniff = true,
+                "--no-compression" => config.no_compression = true,
+                "--compression-level" => match args.next().and_then(|value| value.parse().ok()) {
+                    Some(level) if level <= 9 => config.compression.level = level,
+                    _ => println!("warning: invalid value for {}", arg),
+                },
+                "--compression-min-size" => {
+                    match args.next().and_then(|value| value.parse().ok()) {
+                        Some(min_size) => config.compression.min_size = min_size,
+                        None => println!("warning: invalid value for {}", arg),
+                    }
+                }
+                "--compression-types" => match args.next() {
+                    Some(types) => {
+                        config.compression.types = types
+                            .split(',')
+                            .map(|media_type| media_type.trim().to_string())
+                            .filter(|media_type| !media_type.is_empty())
+                            .collect()
+                    }
+                    None => println!("warning: invalid value for {}", arg),
+                },
+                _ => println!("warning: unknown option {}", arg),
+            }
+        }
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_from_args() {
+        let config = Config::from_args(args(&[
+            "--create-dirs",
+            "--directory",
+            "/tmp",
+            "--no-listing",
+            "--weak-etags",
+            "--mime-types",
+            "/etc/mime.types",
+            "--sniff",
+            "--no-compression",
+            "--compression-level",
+            "9",
+            "--compression-min-size",
+            "1024",
+            "--compression-types",
+            "text/*, application/json",
+        ]));
+
+        assert_eq!(config.directory, Some(PathBuf::from("/tmp")));
+        assert!(config.create_dirs);
+        assert!(config.no_listing);
+        assert!(config.weak_etags);
+        assert_eq!(config.mime_types, Some(PathBuf::from("/etc/mime.types")));
+        assert!(config.sniff);
+        assert!(config.no_compression);
+        assert_eq!(config.compression.level, 9);
+        assert_eq!(config.compression.min_size, 1024);
+        assert_eq!(config.compression.types, vec!["text/*", "application/json"]);
+    }
+
+    #[test]
+    fn test_from_args_invalid_value() {
+        let config = Config::from_args(args(&["--compression-level", "10", "--sniff"]));
+
+        assert_eq!(config.compression.level, 6);
+        assert!(config.sniff);
+    }
+
+    #[test]
+    fn test_from_args_default() {
+        let config = Config::from_args(args(&[]));
+
+        assert_eq!(config.directory, None);
+        assert!(!config.create_dirs);
+        assert!(!config.no_listing);
+        assert!(!config.weak_etags);
+        assert_eq!(config.mime_types, None);
+        assert!(!config.sniff);
+        assert!(!config.no_compression);
+        assert_eq!(config.compression.level, 6);
+        assert_eq!(config.compression.min_size, 0);
+    }
+}
@@ -0,0 +1,219 @@
+//! Entity tags and evaluation of conditional request headers (RFC 9110 section 13)
+use std::{fs, time::UNIX_EPOCH};
+
+use crate::http::{date, headers::HeaderMap};
+
+/// Strong entity tag of a file derived from its size and modification time
+pub fn etag(metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", modified, metadata.len())
+}
+
+/// Check if the strong entity tag matches an element of If-Match
+/// Weak tags never match with the strong comparison
+fn strong_match(element: &str, etag: &str) -> bool {
+    !element.starts_with("W/") && element == etag
+}
+
+/// Check if the entity tag matches an element of If-None-Match
+/// The weak comparison ignores the `W/` prefix of both tags
+fn weak_match(element: &str, etag: &str) -> bool {
+    element.trim_start_matches("W/") == etag.trim_start_matches("W/")
+}
+
+/// Evaluate If-None-Match and If-Modified-Since for a request reading the file
+/// Returns false when the copy of the client is current and the request must be answered with 304
+pub fn check_read(headers: &HeaderMap, metadata: &fs::Metadata) -> bool {
+    // If-Modified-Since is ignored when If-None-Match is present
+    if headers.get("If-None-Match").is_some() {
+        let current_etag = etag(metadata);
+        return !headers
+            .get_list("If-None-Match")
+            .any(|element| element == "*" || weak_match(element, &current_etag));
+    }
+
+    // If-Modified-Since: the file must have been modified after the date
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+    let date = headers
+        .get("If-Modified-Since")
+        .and_then(date::parse_http_date)
+        .and_then(|date| date.duration_since(UNIX_EPOCH).ok());
+    match (modified, date) {
+        (Some(modified), Some(date)) => modified.as_secs() > date.as_secs(),
+        _ => true,
+    }
+}
+
+/// Evaluate If-Match and If-None-Match for a request replacing the file
+/// `current` is the metadata of the existing file, or None when it does not exist
+/// Returns false when the precondition fails and the request must be answered with 412
+pub fn check_write(headers: &HeaderMap, current: Option<&fs::Metadata>) -> bool {
+    let current_etag = current.map(etag);
+
+    // If-Match: the file must exist and, unless `*`, match one of the tags
+    if headers.get("If-Match").is_some() {
+        let Some(current_etag) = &current_etag else {
+            return false;
+        };
+        if !headers
+            .get_list("If-Match")
+            .any(|element| element == "*" || strong_match(element, current_etag))
+        {
+            return false;
+        }
+    }
+
+    // If-None-Match: the file must not exist or, unless `*`, match none of the tags
+    if headers.get("If-None-Match").is_some() {
+        if let Some(current_etag) = &current_etag {
+            if headers
+                .get_list("If-None-Match")
+                .any(|element| element == "*" || weak_match(element, current_etag))
+            {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+/// Evaluate If-Range for a request with a Range header
+/// Returns false when the file changed and the full file must be sent instead of the ranges
+pub fn check_if_range(headers: &HeaderMap, metadata: &fs::Metadata) -> bool {
+    let Some(value) = headers.get("If-Range") else {
+        return true;
+    };
+    // An entity tag must match with the strong comparison
+    if value.starts_with('"') || value.starts_with("W/") {
+        return strong_match(value, &etag(metadata));
+    }
+    // A date must be the exact modification time of the file
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+    let date = date::parse_http_date(value).and_then(|date| date.duration_since(UNIX_EPOCH).ok());
+    match (modified, date) {
+        (Some(modified), Some(date)) => modified.as_secs() == date.as_secs(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(name: &str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.append(name, value);
+        headers
+    }
+
+    #[test]
+    fn test_check_write() {
+        let path = "/tmp/http_server_conditional_check_write";
+        fs::write(path, "abc").unwrap();
+        let metadata = fs::metadata(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let current_etag = etag(&metadata);
+
+        assert!(check_write(&HeaderMap::new(), Some(&metadata)));
+        assert!(check_write(&headers("If-None-Match", "*"), None));
+        assert!(!check_write(
+            &headers("If-None-Match", "*"),
+            Some(&metadata)
+        ));
+        assert!(check_write(&headers("If-Match", "*"), Some(&metadata)));
+        assert!(!check_write(&headers("If-Match", "*"), None));
+        assert!(check_write(
+            &headers("If-Match", &format!("\"x\", {}", current_etag)),
+            Some(&metadata)
+        ));
+        assert!(!check_write(
+            &headers("If-Match", &format!("W/{}", current_etag)),
+            Some(&metadata)
+        ));
+        assert!(!check_write(
+            &headers("If-None-Match", &current_etag),
+            Some(&metadata)
+        ));
+    }
+
+    #[test]
+    fn test_check_if_range() {
+        let path = "/tmp/http_server_conditional_check_if_range";
+        fs::write(path, "abc").unwrap();
+        let metadata = fs::metadata(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let current_etag = etag(&metadata);
+        let modified = date::format_http_date(metadata.modified().unwrap());
+
+        assert!(check_if_range(&HeaderMap::new(), &metadata));
+        assert!(check_if_range(
+            &headers("If-Range", &current_etag),
+            &metadata
+        ));
+        assert!(!check_if_range(
+            &headers("If-Range", &format!("W/{}", current_etag)),
+            &metadata
+        ));
+        assert!(!check_if_range(&headers("If-Range", "\"x\""), &metadata));
+        assert!(check_if_range(&headers("If-Range", &modified), &metadata));
+        assert!(!check_if_range(
+            &headers("If-Range", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            &metadata
+        ));
+        assert!(!check_if_range(
+            &headers("If-Range", "yesterday"),
+            &metadata
+        ));
+    }
+
+    #[test]
+    fn test_check_read() {
+        let path = "/tmp/http_server_conditional_check_read";
+        fs::write(path, "abc").unwrap();
+        let metadata = fs::metadata(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let current_etag = etag(&metadata);
+        let modified = date::format_http_date(metadata.modified().unwrap());
+
+        assert!(check_read(&HeaderMap::new(), &metadata));
+        assert!(!check_read(
+            &headers("If-None-Match", &current_etag),
+            &metadata
+        ));
+        assert!(!check_read(
+            &headers("If-None-Match", &format!("\"x\", W/{}", current_etag)),
+            &metadata
+        ));
+        assert!(!check_read(&headers("If-None-Match", "*"), &metadata));
+        assert!(check_read(&headers("If-None-Match", "\"x\""), &metadata));
+        assert!(!check_read(
+            &headers("If-Modified-Since", &modified),
+            &metadata
+        ));
+        assert!(check_read(
+            &headers("If-Modified-Since", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            &metadata
+        ));
+        assert!(check_read(
+            &headers("If-Modified-Since", "yesterday"),
+            &metadata
+        ));
+
+        // If-Modified-Since is ignored when If-None-Match is present
+        let mut both = headers("If-None-Match", "\"x\"");
+        both.append("If-Modified-Since", &modified);
+        assert!(check_read(&both, &metadata));
+    }
+}
@@ -0,0 +1,200 @@
+//! Directory index of the `/files` routes in HTML or JSON
+use std::{fs, io, path::Path, time::SystemTime};
+
+use crate::http::{
+    date,
+    response::{Response, StatusCode},
+};
+
+/// Entry of a directory index
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Read the entries of the directory sorted by name
+fn read_entries(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Symlinks are listed as they are, without following them
+        let metadata = entry.metadata()?;
+        entries.push(Entry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Create the directory index of `dir`
+/// `url_path` is the path of the directory relative to `/files`, used for the links
+pub fn list_directory(dir: &Path, url_path: &str, json: bool) -> io::Result<Response> {
+    let entries = read_entries(dir)?;
+    let url_path = url_path.trim_matches('/');
+    let base = if url_path.is_empty() {
+        "/files/".to_string()
+    } else {
+        format!("/files/{}/", url_path)
+    };
+
+    let response = Response::new(StatusCode::Ok).header("Vary", "Accept");
+    if json {
+        Ok(response
+            .header("Content-Type", "application/json")
+            .body(render_json(&base, &entries)))
+    } else {
+        Ok(response
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(render_html(&base, &entries)))
+    }
+}
+
+/// Render the index as a JSON object
+/// e.g. `{"path":"/files/","entries":[{"name":"a","type":"file","size":1,"modified":"..."}]}`
+fn render_json(base: &str, entries: &[Entry]) -> String {
+    let entries: Vec<String> = entries
+        .iter()
+        .map(|entry| {
+            format!(
+                "{{\"name\":{},\"type\":\"{}\",\"size\":{},\"modified\":{}}}",
+                json_string(&entry.name),
+                if entry.is_dir { "directory" } else { "file" },
+                entry.size,
+                entry
+                    .modified
+                    .map(|modified| json_string(&date::format_rfc3339(modified)))
+                    .unwrap_or("null".to_string())
+            )
+        })
+        .collect();
+    format!(
+        "{{\"path\":{},\"entries\":[{}]}}",
+        json_string(base),
+        entries.join(",")
+    )
+}
+
+/// Render the index as an HTML table
+fn render_html(base: &str, entries: &[Entry]) -> String {
+    let title = html_escape(base);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {title}</title></head>\n\
+         <body>\n<h1>Index of {title}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n"
+    );
+    for entry in entries {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}{}{}\">{}{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(base),
+            percent_encode(&entry.name),
+            suffix,
+            html_escape(&entry.name),
+            suffix,
+            if entry.is_dir {
+                "-".to_string()
+            } else {
+                entry.size.to_string()
+            },
+            entry
+                .modified
+                .map(date::format_http_date)
+                .unwrap_or("-".to_string())
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Quote the string as a JSON string literal
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Escape the characters with a special meaning in HTML text and attributes
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Percent-encode a file name for use as a path segment of a link
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                name: "a \"b\".txt".to_string(),
+                is_dir: false,
+                size: 3,
+                modified: Some(SystemTime::UNIX_EPOCH),
+            },
+            Entry {
+                name: "<dir>".to_string(),
+                is_dir: true,
+                size: 4096,
+                modified: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_json() {
+        assert_eq!(
+            render_json("/files/", &entries()),
+            "{\"path\":\"/files/\",\"entries\":[\
+             {\"name\":\"a \\\"b\\\".txt\",\"type\":\"file\",\"size\":3,\"modified\":\"1970-01-01T00:00:00Z\"},\
+             {\"name\":\"<dir>\",\"type\":\"directory\",\"size\":4096,\"modified\":null}]}"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html("/files/sub/", &entries());
+
+        assert!(html.contains(
+            "<tr><td><a href=\"/files/sub/a%20%22b%22.txt\">a &quot;b&quot;.txt</a></td>\
+             <td>3</td><td>Thu, 01 Jan 1970 00:00:00 GMT</td></tr>"
+        ));
+        assert!(html.contains(
+            "<tr><td><a href=\"/files/sub/%3Cdir%3E/\">&lt;dir&gt;/</a></td><td>-</td><td>-</td></tr>"
+        ));
+    }
+}
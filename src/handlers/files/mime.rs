@@ -0,0 +1,210 @@
+//! Media types of served files, from their extension or their first bytes
+use std::{collections::HashMap, fs, io, path::Path};
+
+/// Media type of files that are not recognized
+pub const DEFAULT_TYPE: &str = "application/octet-stream";
+
+/// Number of bytes read from a file to sniff its media type
+pub const SNIFF_LEN: usize = 512;
+
+/// Built-in table of extensions and their media types
+const BUILTIN: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("txt", "text/plain"),
+    ("csv", "text/csv"),
+    ("md", "text/markdown"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+/// Magic numbers and the media types they identify
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\0asm", "application/wasm"),
+];
+
+/// Table of file extensions and their media types
+#[derive(Debug)]
+pub struct MimeTypes {
+    by_extension: HashMap<String, String>,
+}
+
+impl Default for MimeTypes {
+    fn default() -> Self {
+        let by_extension = BUILTIN
+            .iter()
+            .map(|(extension, media_type)| (extension.to_string(), media_type.to_string()))
+            .collect();
+        MimeTypes { by_extension }
+    }
+}
+
+impl MimeTypes {
+    /// Add the entries of a `mime.types` file, overriding the existing ones
+    /// Each line is a media type followed by its extensions, e.g. `text/html html htm`
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+        self.extend(&content);
+        Ok(())
+    }
+
+    fn extend(&mut self, content: &str) {
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut fields = line.split_whitespace();
+            let Some(media_type) = fields.next() else {
+                continue;
+            };
+            for extension in fields {
+                self.by_extension
+                    .insert(extension.to_ascii_lowercase(), media_type.to_string());
+            }
+        }
+    }
+
+    /// Media type of the file from its extension, ignoring case
+    pub fn lookup(&self, path: &Path) -> Option<&str> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        self.by_extension.get(&extension).map(String::as_str)
+    }
+}
+
+/// Guess the media type from the first bytes of a file
+/// Unknown content that is valid UTF-8 without control characters is taken as text
+pub fn sniff(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, media_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return Some(media_type);
+    }
+
+    let start = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let head = bytes[start..bytes.len().min(start + 14)].to_ascii_lowercase();
+    if head.starts_with(b"<!doctype html") || head.starts_with(b"<html") {
+        return Some("text/html");
+    }
+
+    // A multi-byte character may be cut at the end of the sniffed bytes
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            let valid = &bytes[..e.valid_up_to()];
+            std::str::from_utf8(valid).ok()?
+        }
+        Err(_) => return None,
+    };
+    if !bytes.is_empty()
+        && !text
+            .chars()
+            .any(|c| c.is_control() && !c.is_ascii_whitespace())
+    {
+        return Some("text/plain");
+    }
+    None
+}
+
+/// Value of the Content-Type header, with `charset=utf-8` for text
+pub fn content_type(media_type: &str) -> String {
+    let is_text = media_type.starts_with("text/")
+        || matches!(
+            media_type,
+            "application/json" | "application/xml" | "image/svg+xml"
+        );
+    if is_text && !media_type.contains(';') {
+        format!("{}; charset=utf-8", media_type)
+    } else {
+        media_type.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let mut mime_types = MimeTypes::default();
+
+        assert_eq!(
+            mime_types.lookup(Path::new("/a/index.HTML")),
+            Some("text/html")
+        );
+        assert_eq!(
+            mime_types.lookup(Path::new("/a/archive.tar.gz")),
+            Some("application/gzip")
+        );
+        assert_eq!(mime_types.lookup(Path::new("/a/README")), None);
+        assert_eq!(mime_types.lookup(Path::new("/a/file.unknown")), None);
+
+        mime_types.extend("# comment\ntext/x-rust rs\n\napplication/x-data json dat # data\n");
+        assert_eq!(mime_types.lookup(Path::new("main.rs")), Some("text/x-rust"));
+        assert_eq!(
+            mime_types.lookup(Path::new("a.json")),
+            Some("application/x-data")
+        );
+        assert_eq!(
+            mime_types.lookup(Path::new("a.dat")),
+            Some("application/x-data")
+        );
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), Some("image/png"));
+        assert_eq!(sniff(b"%PDF-1.7"), Some("application/pdf"));
+        assert_eq!(sniff(b"\n  <!DOCTYPE html><html>"), Some("text/html"));
+        assert_eq!(sniff(b"hello\nworld\t!"), Some("text/plain"));
+        // Cut in the middle of `é`
+        assert_eq!(sniff(b"caf\xc3"), Some("text/plain"));
+        assert_eq!(sniff(b"\0\x01\x02"), None);
+        assert_eq!(sniff(b"\xff\xfe"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type("text/html"), "text/html; charset=utf-8");
+        assert_eq!(
+            content_type("application/json"),
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            content_type("text/plain; charset=latin1"),
+            "text/plain; charset=latin1"
+        );
+        assert_eq!(content_type("image/png"), "image/png");
+    }
+}
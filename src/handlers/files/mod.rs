@@ -0,0 +1,301 @@
+//! Handlers for `/files/*path`, serving files from the `--directory` root
+use std::{
+    fs,
+    io::{self, prelude::*},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::http::{
+    date,
+    request::Reqeuest,
+    response::{Response, StatusCode},
+    router::Params,
+};
+
+mod conditional;
+mod listing;
+mod mime;
+mod path;
+mod range;
+
+pub use mime::MimeTypes;
+
+/// Options of the `/files` routes
+#[derive(Debug)]
+pub struct FilesConfig {
+    /// Directory the files are served from and uploaded into
+    pub root: PathBuf,
+    /// Create missing parent directories on upload instead of answering 404
+    pub create_dirs: bool,
+    /// List the entries of directories instead of answering 404
+    pub listing: bool,
+    /// Send `ETag` as a weak entity tag, e.g. `W/"..."`
+    pub weak_etags: bool,
+    /// Media types of files by extension
+    pub mime_types: MimeTypes,
+    /// Guess the media type of files without a known extension from their first bytes
+    pub sniff: bool,
+}
+
+/// `GET /files/*path`
+/// Directories are listed as HTML, or as JSON when the client accepts `application/json`
+pub fn get_file(request: &Reqeuest, params: &Params, config: &FilesConfig) -> Response {
+    // Get the filename and contents of file
+    let file_path = match resolve_path(params, config) {
+        Ok(file_path) => file_path,
+        Err(response) => return response,
+    };
+    if file_path.is_dir() && config.listing {
+        let path = params.get("path").unwrap_or("");
+        let json = accepts_json(request);
+        return listing::list_directory(&file_path, path, json)
+            .unwrap_or_else(|e| io_error_response(&e));
+    }
+    // Directories without listing and missing intermediate directories are not found
+    if !file_path.is_file() {
+        return Response::new(StatusCode::NotFound);
+    }
+    send_file(request, &file_path, config).unwrap_or_else(|e| io_error_response(&e))
+}
+
+/// `POST /files/*path`
+pub fn post_file(request: &Reqeuest, params: &Params, config: &FilesConfig) -> Response {
+    // If the content type is not application/octet-stream, return 415
+    if request.headers.media_type().as_deref() != Some("application/octet-stream") {
+        return Response::new(StatusCode::UnsupportedMediaType);
+    }
+
+    // Get the filename
+    let file_path = match upload_path(params, config) {
+        Ok(file_path) => file_path,
+        Err(response) => return response,
+    };
+
+    // Create the file and write the contents
+    // The reader already limited the body to Content-Length bytes
+    match fs::File::create(file_path).and_then(|mut file| file.write_all(&request.body)) {
+        Ok(()) => Response::new(StatusCode::Created),
+        Err(e) => io_error_response(&e),
+    }
+}
+
+/// `PUT /files/*path`
+/// Creates (201) or replaces (204) the file with the body
+/// `If-Match` and `If-None-Match: *` are answered with 412 when they do not hold
+pub fn put_file(request: &Reqeuest, params: &Params, config: &FilesConfig) -> Response {
+    let file_path = match upload_path(params, config) {
+        Ok(file_path) => file_path,
+        Err(response) => return response,
+    };
+
+    let current = match fs::metadata(&file_path) {
+        Ok(metadata) if metadata.is_dir() => return Response::new(StatusCode::Conflict),
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return io_error_response(&e),
+    };
+    if !conditional::check_write(&request.headers, current.as_ref()) {
+        return Response::new(StatusCode::PreconditionFailed);
+    }
+
+    // Only create the file when it did not exist, so that a concurrent writer
+    // that created it in the meantime is not clobbered
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    match current {
+        Some(_) => options.truncate(true),
+        None => options.create_new(true),
+    };
+    match options
+        .open(file_path)
+        .and_then(|mut file| file.write_all(&request.body))
+    {
+        Ok(()) if current.is_some() => Response::new(StatusCode::NoContent),
+        Ok(()) => Response::new(StatusCode::Created),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            Response::new(StatusCode::PreconditionFailed)
+        }
+        Err(e) => io_error_response(&e),
+    }
+}
+
+/// `DELETE /files/*path`
+/// Removes a file or an empty directory; a non-empty directory is a conflict
+pub fn delete_file(_request: &Reqeuest, params: &Params, config: &FilesConfig) -> Response {
+    let file_path = match resolve_path(params, config) {
+        Ok(file_path) => file_path,
+        Err(response) => return response,
+    };
+    // The root directory itself cannot be removed
+    if params
+        .get("path")
+        .unwrap_or("")
+        .trim_matches('/')
+        .is_empty()
+    {
+        return Response::new(StatusCode::Forbidden);
+    }
+
+    // Do not follow symlinks, removing a link only removes the link itself
+    let result = match fs::symlink_metadata(&file_path) {
+        Ok(metadata) if metadata.is_dir() => match fs::read_dir(&file_path) {
+            Ok(mut entries) => {
+                if entries.next().is_some() {
+                    return Response::new(StatusCode::Conflict);
+                }
+                fs::remove_dir(&file_path)
+            }
+            Err(e) => Err(e),
+        },
+        Ok(_) => fs::remove_file(&file_path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => Response::new(StatusCode::NoContent),
+        Err(e) => io_error_response(&e),
+    }
+}
+
+/// Resolve the `path` parameter to a path confined to the root directory
+/// Traversal outside the root is answered with 403
+fn resolve_path(params: &Params, config: &FilesConfig) -> Result<PathBuf, Response> {
+    let path = params.get("path").unwrap_or("");
+    path::resolve(&config.root, path).map_err(|e| match e {
+        path::PathError::Forbidden => Response::new(StatusCode::Forbidden),
+        path::PathError::RootNotFound => Response::new(StatusCode::NotFound),
+    })
+}
+
+/// Resolve the path of a file to be uploaded
+/// Missing parent directories are created when allowed and answered with 404 otherwise
+fn upload_path(params: &Params, config: &FilesConfig) -> Result<PathBuf, Response> {
+    let file_path = resolve_path(params, config)?;
+    // A file name is required, e.g. `/files/` or `/files/dir/` cannot be created
+    let path = params.get("path").unwrap_or("");
+    if path.is_empty() || path.ends_with('/') {
+        return Err(Response::new(StatusCode::NotFound));
+    }
+
+    // Create the parent directories if allowed
+    if let Some(parent) = file_path.parent() {
+        if !parent.is_dir() {
+            if !config.create_dirs {
+                return Err(Response::new(StatusCode::NotFound));
+            }
+            fs::create_dir_all(parent).map_err(|e| io_error_response(&e))?;
+        }
+    }
+    Ok(file_path)
+}
+
+/// Send the file, or the ranges of it asked for with the Range header
+/// The file is streamed instead of being read into memory
+/// A current copy of the client, per If-None-Match or If-Modified-Since, is answered with 304
+fn send_file(request: &Reqeuest, file_path: &Path, config: &FilesConfig) -> io::Result<Response> {
+    let mut file = fs::File::open(file_path)?;
+    let metadata = file.metadata()?;
+    let len = metadata.len();
+    let content_type = mime::content_type(&media_type(&mut file, file_path, config)?);
+    let content_type = content_type.as_str();
+
+    // Validators of the file, sent with every response that describes it
+    let etag = match config.weak_etags {
+        true => format!("W/{}", conditional::etag(&metadata)),
+        false => conditional::etag(&metadata),
+    };
+    let last_modified = metadata.modified().ok().map(date::format_http_date);
+    let validators = |response: Response| match &last_modified {
+        Some(last_modified) => response
+            .header("ETag", &etag)
+            .header("Last-Modified", last_modified),
+        None => response.header("ETag", &etag),
+    };
+    if !conditional::check_read(&request.headers, &metadata) {
+        return Ok(validators(Response::new(StatusCode::NotModified)));
+    }
+
+    let ranges = match request.headers.get("Range") {
+        Some(value) if conditional::check_if_range(&request.headers, &metadata) => {
+            range::parse(value, len)
+        }
+        _ => range::Ranges::Full,
+    };
+    let response = match ranges {
+        range::Ranges::Full => validators(Response::new(StatusCode::Ok))
+            .header("Content-Type", content_type)
+            .header("X-Content-Type-Options", "nosniff")
+            .header("Accept-Ranges", "bytes")
+            .stream(file, Some(len)),
+        range::Ranges::Unsatisfiable => Response::new(StatusCode::RangeNotSatisfiable)
+            .header("Accept-Ranges", "bytes")
+            .header("Content-Range", &format!("bytes */{}", len)),
+        range::Ranges::Satisfiable(ranges) if ranges.len() == 1 => {
+            let range = ranges[0];
+            file.seek(io::SeekFrom::Start(range.start))?;
+            validators(Response::new(StatusCode::PartialContent))
+                .header("Content-Type", content_type)
+                .header("X-Content-Type-Options", "nosniff")
+                .header("Accept-Ranges", "bytes")
+                .header("Content-Range", &range.content_range(len))
+                .stream(file.take(range.len()), Some(range.len()))
+        }
+        range::Ranges::Satisfiable(ranges) => {
+            // The boundary only has to be absent from the parts, the time makes it unique enough
+            let boundary = format!(
+                "{:032x}",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_nanos())
+                    .unwrap_or(0)
+            );
+            let (reader, body_len) =
+                range::MultipartReader::new(file, &ranges, len, content_type, &boundary);
+            validators(Response::new(StatusCode::PartialContent))
+                .header(
+                    "Content-Type",
+                    &format!("multipart/byteranges; boundary={}", boundary),
+                )
+                .header("X-Content-Type-Options", "nosniff")
+                .header("Accept-Ranges", "bytes")
+                .stream(reader, Some(body_len))
+        }
+    };
+    Ok(response)
+}
+
+/// Media type of the file from its extension or, when enabled, from its first bytes
+fn media_type(file: &mut fs::File, file_path: &Path, config: &FilesConfig) -> io::Result<String> {
+    if let Some(media_type) = config.mime_types.lookup(file_path) {
+        return Ok(media_type.to_string());
+    }
+    if !config.sniff {
+        return Ok(mime::DEFAULT_TYPE.to_string());
+    }
+    let mut head = Vec::with_capacity(mime::SNIFF_LEN);
+    Read::by_ref(file)
+        .take(mime::SNIFF_LEN as u64)
+        .read_to_end(&mut head)?;
+    file.rewind()?;
+    Ok(mime::sniff(&head).unwrap_or(mime::DEFAULT_TYPE).to_string())
+}
+
+/// Whether the `Accept` header asks for `application/json` with a non-zero q-value
+fn accepts_json(request: &Reqeuest) -> bool {
+    request
+        .headers
+        .get_weighted("Accept")
+        .any(|(range, q)| range.eq_ignore_ascii_case("application/json") && q > 0)
+}
+
+/// Response for a failed file operation
+fn io_error_response(e: &io::Error) -> Response {
+    match e.kind() {
+        io::ErrorKind::NotFound => Response::new(StatusCode::NotFound),
+        io::ErrorKind::PermissionDenied => Response::new(StatusCode::Forbidden),
+        _ => {
+            println!("error: {}", e);
+            Response::new(StatusCode::InternalServerError)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Resolution of request paths to files confined to the `--directory` root
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Reason a request path cannot be mapped to a file under the root
+#[derive(Debug, PartialEq, Eq)]
+pub enum PathError {
+    /// The path points outside the root, e.g. `../etc/passwd` or a symlink to `/etc`
+    Forbidden,
+    /// The root directory itself cannot be accessed
+    RootNotFound,
+}
+
+/// Map the (percent-encoded) path relative to the root to a file path
+/// `..` components and paths that resolve outside the root through symlinks are rejected
+/// The file itself does not need to exist, so the result can be used to create files
+pub fn resolve(root: &Path, name: &str) -> Result<PathBuf, PathError> {
+    let name = percent_decode(name);
+
+    let mut path = root.to_path_buf();
+    for component in name.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return Err(PathError::Forbidden),
+            // Backslashes and NUL bytes are never part of a legitimate file name
+            _ if component.contains(['\\', '\0']) => return Err(PathError::Forbidden),
+            _ => path.push(component),
+        }
+    }
+
+    // Follow symlinks of the longest existing prefix and check it is still under the root
+    let root = root.canonicalize().map_err(|_| PathError::RootNotFound)?;
+    let existing = canonicalize_existing(&path).map_err(|_| PathError::Forbidden)?;
+    if !existing.starts_with(&root) {
+        return Err(PathError::Forbidden);
+    }
+
+    Ok(path)
+}
+
+/// Canonicalize the longest prefix of the path that exists
+fn canonicalize_existing(path: &Path) -> io::Result<PathBuf> {
+    let mut ancestors = path.ancestors();
+    loop {
+        let Some(ancestor) = ancestors.next() else {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        };
+        match ancestor.canonicalize() {
+            Ok(canonical) => return Ok(canonical),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decode `%XX` escapes so that `..%2F` is treated like `../`
+/// Malformed escapes are kept as they are
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::symlink};
+
+    #[test]
+    fn test_resolve_inside_root() {
+        let root = Path::new("/tmp");
+
+        assert_eq!(resolve(root, "foo"), Ok(PathBuf::from("/tmp/foo")));
+        assert_eq!(
+            resolve(root, "./foo%20bar"),
+            Ok(PathBuf::from("/tmp/foo bar"))
+        );
+    }
+
+    #[test]
+    fn test_resolve_traversal() {
+        let root = Path::new("/tmp");
+
+        for name in [
+            "..",
+            "../etc/passwd",
+            "..%2F..%2Fetc%2Fpasswd",
+            "%2e%2e/x",
+            "a\\..\\b",
+        ] {
+            assert_eq!(resolve(root, name), Err(PathError::Forbidden), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_resolve_symlink_escape() {
+        let root = Path::new("/tmp/http_server_resolve_symlink");
+        fs::create_dir_all(root).unwrap();
+        let link = root.join("etc");
+        let _ = fs::remove_file(&link);
+        symlink("/etc", &link).unwrap();
+
+        let result = resolve(root, "etc/passwd");
+        fs::remove_dir_all(root).unwrap();
+
+        assert_eq!(result, Err(PathError::Forbidden));
+    }
+}
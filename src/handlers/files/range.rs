@@ -0,0 +1,278 @@
+//! Byte ranges of file downloads (RFC 9110 section 14)
+use std::{
+    collections::VecDeque,
+    fs,
+    io::{self, prelude::*, Cursor, SeekFrom},
+};
+
+/// More ranges than this are ignored and the full file is sent instead
+const MAX_RANGES: usize = 32;
+
+/// Inclusive range of bytes of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Value of the Content-Range header, e.g. `bytes 0-499/1234`
+    pub fn content_range(&self, complete_len: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, complete_len)
+    }
+}
+
+/// Outcome of evaluating the Range header against a file
+#[derive(Debug, PartialEq, Eq)]
+pub enum Ranges {
+    /// The header is ignored and the full file is sent
+    Full,
+    /// The satisfiable ranges, in the requested order
+    Satisfiable(Vec<ByteRange>),
+    /// None of the ranges overlaps the file, answered with 416
+    Unsatisfiable,
+}
+
+/// Parse a Range header for a file of `len` bytes
+/// e.g. `bytes=0-499`, `bytes=500-`, `bytes=-500` or `bytes=0-0,-1`
+/// Invalid headers and other range units are ignored
+pub fn parse(value: &str, len: u64) -> Ranges {
+    let Some((unit, specs)) = value.split_once('=') else {
+        return Ranges::Full;
+    };
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return Ranges::Full;
+    }
+
+    let mut ranges = Vec::new();
+    let mut count = 0;
+    for spec in specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+    {
+        count += 1;
+        let Some((first, last)) = spec.split_once('-') else {
+            return Ranges::Full;
+        };
+        let range = match (parse_position(first), parse_position(last)) {
+            // `-500`: the last 500 bytes
+            (None, Some(suffix)) if first.is_empty() => {
+                if suffix == 0 || len == 0 {
+                    continue;
+                }
+                ByteRange {
+                    start: len.saturating_sub(suffix),
+                    end: len - 1,
+                }
+            }
+            // `500-`: from byte 500 to the end
+            (Some(start), None) if last.is_empty() => ByteRange {
+                start,
+                end: len.saturating_sub(1),
+            },
+            // `0-499`, the last position is clamped to the end of the file
+            (Some(start), Some(end)) if start <= end => ByteRange {
+                start,
+                end: end.min(len.saturating_sub(1)),
+            },
+            _ => return Ranges::Full,
+        };
+        // Ranges starting past the end are not satisfiable
+        if range.start < len {
+            ranges.push(range);
+        }
+    }
+
+    if count == 0 || count > MAX_RANGES {
+        Ranges::Full
+    } else if ranges.is_empty() {
+        Ranges::Unsatisfiable
+    } else {
+        Ranges::Satisfiable(ranges)
+    }
+}
+
+/// Parse a position made of digits only
+fn parse_position(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+/// Part of a `multipart/byteranges` body
+enum Part {
+    /// Boundary and part headers
+    Bytes(Cursor<Vec<u8>>),
+    /// Range of the file, seeked to when it is reached
+    File {
+        start: u64,
+        remaining: u64,
+        seeked: bool,
+    },
+}
+
+/// Reader of a `multipart/byteranges` body (RFC 9110 section 14.6)
+/// Reads the ranges from the file one after the other in bounded memory
+pub struct MultipartReader {
+    file: fs::File,
+    parts: VecDeque<Part>,
+}
+
+impl MultipartReader {
+    /// Create the reader and the length of the body
+    pub fn new(
+        file: fs::File,
+        ranges: &[ByteRange],
+        complete_len: u64,
+        content_type: &str,
+        boundary: &str,
+    ) -> (Self, u64) {
+        let mut parts = VecDeque::new();
+        let mut len = 0;
+        for range in ranges {
+            let head = format!(
+                "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                boundary,
+                content_type,
+                range.content_range(complete_len)
+            );
+            len += head.len() as u64 + range.len();
+            parts.push_back(Part::Bytes(Cursor::new(head.into_bytes())));
+            parts.push_back(Part::File {
+                start: range.start,
+                remaining: range.len(),
+                seeked: false,
+            });
+        }
+        let tail = format!("\r\n--{}--\r\n", boundary);
+        len += tail.len() as u64;
+        parts.push_back(Part::Bytes(Cursor::new(tail.into_bytes())));
+
+        (MultipartReader { file, parts }, len)
+    }
+}
+
+impl Read for MultipartReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(part) = self.parts.front_mut() {
+            let n = match part {
+                Part::Bytes(cursor) => cursor.read(buf)?,
+                Part::File {
+                    start,
+                    remaining,
+                    seeked,
+                } => {
+                    if !*seeked {
+                        self.file.seek(SeekFrom::Start(*start))?;
+                        *seeked = true;
+                    }
+                    let limit = buf
+                        .len()
+                        .min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+                    let n = self.file.read(&mut buf[..limit])?;
+                    // The file was truncated while it was being sent
+                    if n == 0 && *remaining > 0 && limit > 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    *remaining -= n as u64;
+                    if *remaining > 0 {
+                        return Ok(n);
+                    }
+                    n
+                }
+            };
+            if n > 0 {
+                return Ok(n);
+            }
+            self.parts.pop_front();
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> ByteRange {
+        ByteRange { start, end }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("bytes=0-499", 1000),
+            Ranges::Satisfiable(vec![range(0, 499)])
+        );
+        assert_eq!(
+            parse("bytes=500-", 1000),
+            Ranges::Satisfiable(vec![range(500, 999)])
+        );
+        assert_eq!(
+            parse("bytes=-300", 1000),
+            Ranges::Satisfiable(vec![range(700, 999)])
+        );
+        assert_eq!(
+            parse("bytes=-3000", 1000),
+            Ranges::Satisfiable(vec![range(0, 999)])
+        );
+        assert_eq!(
+            parse("bytes=900-5000", 1000),
+            Ranges::Satisfiable(vec![range(900, 999)])
+        );
+        assert_eq!(
+            parse("bytes=0-0, -1", 1000),
+            Ranges::Satisfiable(vec![range(0, 0), range(999, 999)])
+        );
+        assert_eq!(
+            parse("bytes=0-1,5000-", 1000),
+            Ranges::Satisfiable(vec![range(0, 1)])
+        );
+    }
+
+    #[test]
+    fn test_parse_unsatisfiable() {
+        assert_eq!(parse("bytes=1000-", 1000), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=-0", 1000), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=0-", 0), Ranges::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_parse_ignored() {
+        assert_eq!(parse("items=0-1", 1000), Ranges::Full);
+        assert_eq!(parse("bytes=1-0", 1000), Ranges::Full);
+        assert_eq!(parse("bytes=a-b", 1000), Ranges::Full);
+        assert_eq!(parse("bytes=+1-2", 1000), Ranges::Full);
+        assert_eq!(parse("bytes=", 1000), Ranges::Full);
+        assert_eq!(parse("bytes=1", 1000), Ranges::Full);
+        let many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(parse(&format!("bytes={}", many), 1000), Ranges::Full);
+    }
+
+    #[test]
+    fn test_multipart_reader() {
+        let path = "/tmp/http_server_range_multipart_reader";
+        fs::write(path, "0123456789").unwrap();
+        let file = fs::File::open(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let ranges = [range(0, 1), range(8, 9)];
+        let (mut reader, len) = MultipartReader::new(file, &ranges, 10, "text/plain", "XYZ");
+        let mut body = String::new();
+        reader.read_to_string(&mut body).unwrap();
+
+        assert_eq!(
+            body,
+            "\r\n--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\
+             \r\n--XYZ\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\
+             \r\n--XYZ--\r\n"
+        );
+        assert_eq!(body.len() as u64, len);
+    }
+}
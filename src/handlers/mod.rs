@@ -0,0 +1,29 @@
+//! Handlers for the routes of the server
+use crate::http::{
+    request::Reqeuest,
+    response::{Response, StatusCode},
+    router::Params,
+};
+
+pub mod files;
+
+/// `GET /`
+pub fn root(_request: &Reqeuest, _params: &Params) -> Response {
+    Response::new(StatusCode::Ok)
+}
+
+/// `GET /echo/:text`
+pub fn echo(_request: &Reqeuest, params: &Params) -> Response {
+    let text = params.get("text").unwrap_or("");
+    Response::new(StatusCode::Ok)
+        .header("Content-Type", "text/plain")
+        .body(text)
+}
+
+/// `GET /user-agent`
+pub fn user_agent(request: &Reqeuest, _params: &Params) -> Response {
+    let user_agent = request.headers.user_agent().unwrap_or("").to_string();
+    Response::new(StatusCode::Ok)
+        .header("Content-Type", "text/plain")
+        .body(user_agent)
+}
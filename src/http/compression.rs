@@ -0,0 +1,149 @@
+//! Compression of responses with the coding negotiated from `Accept-Encoding`
+use super::{
+    encoding::{self, NotAcceptable},
+    request::Reqeuest,
+    response::{Response, StatusCode},
+};
+
+/// Media types compressed by default, `type/*` matches all subtypes
+const DEFAULT_TYPES: &[&str] = &[
+    "text/*",
+    "application/json",
+    "application/javascript",
+    "application/xml",
+    "application/wasm",
+    "image/svg+xml",
+];
+
+/// Options of the response compression
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    /// Compression level from 0 (fastest) to 9 (smallest)
+    pub level: u32,
+    /// Bodies smaller than this are sent uncompressed; streams of unknown length are compressed
+    pub min_size: u64,
+    /// Media types that are compressed, e.g. `text/*` or `application/json`
+    pub types: Vec<String>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        CompressionConfig {
+            level: 6,
+            min_size: 0,
+            types: DEFAULT_TYPES
+                .iter()
+                .map(|media_type| media_type.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Compress the response when the client accepts a supported coding
+    /// Only non-empty `200 OK` responses of an allowed media type without
+    /// a Content-Encoding of their own are compressed
+    pub fn apply(&self, request: &Reqeuest, response: Response) -> Response {
+        if response.status() != StatusCode::Ok
+            || response.headers().get("Content-Encoding").is_some()
+            || matches!(response.body_len(), Some(len) if len == 0 || len < self.min_size)
+        {
+            return response;
+        }
+        match response.headers().media_type() {
+            Some(media_type) if self.compressible(&media_type) => {}
+            _ => return response,
+        }
+
+        // The response depends on Accept-Encoding, also when it is not compressed
+        let response = if response.headers().contains_token("Vary", "Accept-Encoding") {
+            response
+        } else {
+            response.header("Vary", "Accept-Encoding")
+        };
+        match encoding::negotiate(&request.headers) {
+            Ok(Some(coding)) => response.encode(coding, self.level),
+            Ok(None) => response,
+            Err(NotAcceptable) => {
+                Response::new(StatusCode::NotAcceptable).header("Vary", "Accept-Encoding")
+            }
+        }
+    }
+
+    /// Check if the media type is in the allowlist
+    fn compressible(&self, media_type: &str) -> bool {
+        self.types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(prefix) => media_type
+                    .split_once('/')
+                    .is_some_and(|(top_level, _)| top_level.eq_ignore_ascii_case(prefix)),
+                None => media_type.eq_ignore_ascii_case(allowed),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::RawRequest;
+
+    fn request(accept_encoding: &str) -> Reqeuest {
+        let head = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: {}",
+            accept_encoding
+        );
+        Reqeuest::new(RawRequest {
+            head: head.into_bytes(),
+            body: Vec::new(),
+            trailers: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    fn text(body: &str) -> Response {
+        Response::new(StatusCode::Ok)
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(body)
+    }
+
+    #[test]
+    fn test_apply() {
+        let config = CompressionConfig {
+            min_size: 4,
+            ..CompressionConfig::default()
+        };
+
+        let response = config.apply(&request("gzip"), text("abcd"));
+        assert_eq!(response.headers().get("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.headers().get("Vary"), Some("Accept-Encoding"));
+
+        let response = config.apply(&request("identity"), text("abcd"));
+        assert_eq!(response.headers().get("Content-Encoding"), None);
+        assert_eq!(response.headers().get("Vary"), Some("Accept-Encoding"));
+
+        let response = config.apply(&request("gzip;q=0, identity;q=0"), text("abcd"));
+        assert_eq!(response.status(), StatusCode::NotAcceptable);
+    }
+
+    #[test]
+    fn test_apply_skipped() {
+        let config = CompressionConfig {
+            min_size: 4,
+            ..CompressionConfig::default()
+        };
+        let octets = Response::new(StatusCode::Ok)
+            .header("Content-Type", "application/octet-stream")
+            .body("abcd");
+        let encoded = text("abcd").header("Content-Encoding", "br");
+        let created = Response::new(StatusCode::Created)
+            .header("Content-Type", "text/plain")
+            .body("abcd");
+
+        for response in [text("abc"), octets, encoded, created] {
+            let response = config.apply(&request("gzip"), response);
+            assert_eq!(response.headers().get("Vary"), None);
+            assert_ne!(response.headers().get("Content-Encoding"), Some("gzip"));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Formatting and parsing of timestamps without an external date library
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Broken-down UTC time
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    /// Days since the epoch, which was a Thursday
+    days: i64,
+}
+
+impl DateTime {
+    fn from_system_time(time: SystemTime) -> Self {
+        // Times before the epoch are clamped to the epoch
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let days = (secs / 86400) as i64;
+        let (year, month, day) = civil_from_days(days);
+        let secs_of_day = secs % 86400;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: secs_of_day / 3600,
+            minute: secs_of_day % 3600 / 60,
+            second: secs_of_day % 60,
+            days,
+        }
+    }
+}
+
+/// Format the time as an IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn format_http_date(time: SystemTime) -> String {
+    let date = DateTime::from_system_time(time);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[date.days.rem_euclid(7) as usize],
+        date.day,
+        MONTHS[date.month as usize - 1],
+        date.year,
+        date.hour,
+        date.minute,
+        date.second
+    )
+}
+
+/// Format the time as RFC 3339 in UTC, e.g. `1994-11-06T08:49:37Z`
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let date = DateTime::from_system_time(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        date.year, date.month, date.day, date.hour, date.minute, date.second
+    )
+}
+
+/// Parse an HTTP-date (RFC 9110 section 5.6.7)
+/// Accepts the IMF-fixdate and the obsolete RFC 850 and asctime formats, e.g.
+/// `Sun, 06 Nov 1994 08:49:37 GMT`, `Sunday, 06-Nov-94 08:49:37 GMT`, `Sun Nov  6 08:49:37 1994`
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let (day, month, year, time) = match parts[..] {
+        // IMF-fixdate
+        [_, day, month, year, time, "GMT"] => (day, month, year.parse().ok()?, time),
+        // RFC 850, two digit years are taken as 1970-2069
+        [_, date, time, "GMT"] => {
+            let mut date = date.split('-');
+            let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+            if date.next().is_some() || year.len() != 2 {
+                return None;
+            }
+            let year: i64 = year.parse().ok()?;
+            (
+                day,
+                month,
+                if year < 70 { 2000 + year } else { 1900 + year },
+                time,
+            )
+        }
+        // asctime
+        [_, month, day, time, year] => (day, month, year.parse().ok()?, time),
+        _ => return None,
+    };
+
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+    let mut time = time.split(':').map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if time.next().is_some() || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60
+    {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day as u32);
+    if days < 0 {
+        return None;
+    }
+    let secs = days as u64 * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Convert (year, month, day) to days since the epoch
+/// Algorithm from http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Convert days since the epoch to (year, month, day)
+/// Algorithm from http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_http_date() {
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+
+        assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(
+            format_http_date(UNIX_EPOCH),
+            "Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        let time = UNIX_EPOCH + Duration::from_secs(951782400);
+
+        assert_eq!(format_rfc3339(time), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        let time = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), time);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), time);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), time);
+        assert_eq!(
+            parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(951782400))
+        );
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49 GMT"), None);
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! Content codings and their negotiation with `Accept-Encoding` (RFC 9110 section 12.5.3)
+use std::io::Read;
+
+#[cfg(feature = "deflate")]
+use flate2::read::ZlibEncoder;
+use flate2::{
+    read::{GzEncoder, MultiGzDecoder, ZlibDecoder},
+    Compression,
+};
+
+use super::headers::HeaderMap;
+
+/// Content codings the server can apply to a response, other than identity
+/// Codings other than gzip are enabled by the cargo features of the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    #[cfg(feature = "br")]
+    Brotli,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    Gzip,
+    #[cfg(feature = "deflate")]
+    Deflate,
+}
+
+impl ContentCoding {
+    /// Supported codings in order of preference of the server
+    pub const ALL: &'static [ContentCoding] = &[
+        #[cfg(feature = "br")]
+        ContentCoding::Brotli,
+        #[cfg(feature = "zstd")]
+        ContentCoding::Zstd,
+        ContentCoding::Gzip,
+        #[cfg(feature = "deflate")]
+        ContentCoding::Deflate,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "br")]
+            ContentCoding::Brotli => "br",
+            #[cfg(feature = "zstd")]
+            ContentCoding::Zstd => "zstd",
+            ContentCoding::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            ContentCoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Compress what is read from the reader with the coding
+/// `level` is from 0 (fastest) to 9 (smallest)
+pub fn encoder<R>(coding: ContentCoding, reader: R, level: u32) -> Box<dyn Read + Send>
+where
+    R: Read + Send + 'static,
+{
+    match coding {
+        // Brotli qualities go up to 11 and the window is the default of 4 MiB
+        #[cfg(feature = "br")]
+        ContentCoding::Brotli => Box::new(brotli::CompressorReader::new(reader, 4096, level, 22)),
+        // Zstandard levels go up to 22, 0 is its default level
+        #[cfg(feature = "zstd")]
+        ContentCoding::Zstd => Box::new(
+            zstd::stream::read::Encoder::new(reader, level as i32)
+                .expect("creating a zstd context only fails when out of memory"),
+        ),
+        ContentCoding::Gzip => Box::new(GzEncoder::new(reader, Compression::new(level))),
+        // `deflate` is the zlib format (RFC 9110 section 8.4.1.2)
+        #[cfg(feature = "deflate")]
+        ContentCoding::Deflate => Box::new(ZlibEncoder::new(reader, Compression::new(level))),
+    }
+}
+
+/// Decompress what is read from the reader with the coding of a request body
+/// Returns None for codings the server cannot decode
+/// `x-gzip` is an alias of gzip (RFC 9110 section 8.4.1.3)
+pub fn decoder<'a, R>(coding: &str, reader: R) -> Option<Box<dyn Read + 'a>>
+where
+    R: Read + 'a,
+{
+    if coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip") {
+        Some(Box::new(MultiGzDecoder::new(reader)))
+    } else if coding.eq_ignore_ascii_case("deflate") {
+        Some(Box::new(ZlibDecoder::new(reader)))
+    } else {
+        None
+    }
+}
+
+/// None of the codings, not even identity, is acceptable to the client
+#[derive(Debug, PartialEq, Eq)]
+pub struct NotAcceptable;
+
+/// Choose the coding of a response from the `Accept-Encoding` header
+/// Returns None for identity, which is also used without the header
+/// Among the codings with the highest q-value the server preference wins,
+/// and identity is only chosen over them when it is listed with a higher q-value
+pub fn negotiate(headers: &HeaderMap) -> Result<Option<ContentCoding>, NotAcceptable> {
+    if headers.get("Accept-Encoding").is_none() {
+        return Ok(None);
+    }
+
+    // q-value of a coding: its own element, else `*`, else not acceptable
+    let weight = |coding: &str| {
+        let mut any = None;
+        for (element, q) in headers.get_weighted("Accept-Encoding") {
+            if element.eq_ignore_ascii_case(coding) {
+                return Some(q);
+            }
+            if element == "*" && any.is_none() {
+                any = Some(q);
+            }
+        }
+        any
+    };
+
+    let mut best: Option<(ContentCoding, u16)> = None;
+    for &coding in ContentCoding::ALL {
+        let q = weight(coding.as_str()).unwrap_or(0);
+        if q > 0 && best.map_or(true, |(_, best_q)| q > best_q) {
+            best = Some((coding, q));
+        }
+    }
+    // Identity is acceptable unless excluded explicitly or by `*;q=0`,
+    // but only preferred over the listed codings with a higher explicit q-value
+    let identity = weight("identity");
+
+    match (best, identity) {
+        (Some((coding, q)), identity) if q >= identity.unwrap_or(0) => Ok(Some(coding)),
+        (_, Some(0)) => Err(NotAcceptable),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn negotiate_value(value: &str) -> Result<Option<ContentCoding>, NotAcceptable> {
+        let mut headers = HeaderMap::new();
+        headers.append("Accept-Encoding", value);
+        negotiate(&headers)
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let gzip = Ok(Some(ContentCoding::Gzip));
+        let preferred = Ok(Some(ContentCoding::ALL[0]));
+
+        assert_eq!(negotiate(&HeaderMap::new()), Ok(None));
+        assert_eq!(negotiate_value(""), Ok(None));
+        assert_eq!(negotiate_value("gzip"), gzip);
+        assert_eq!(negotiate_value("GZIP;q=0.5"), gzip);
+        assert_eq!(negotiate_value("compress, gzip ;q=0.1"), gzip);
+        assert_eq!(negotiate_value("*"), preferred);
+        assert_eq!(negotiate_value("compress, *;q=0.2"), preferred);
+        assert_eq!(negotiate_value("gzip;q=0"), Ok(None));
+        assert_eq!(negotiate_value("*;q=0, gzip;q=0"), Err(NotAcceptable));
+        assert_eq!(negotiate_value("gzip;q=0, compress"), Ok(None));
+        assert_eq!(negotiate_value("gzip;q=0.5, identity"), Ok(None));
+        assert_eq!(negotiate_value("compress"), Ok(None));
+        assert_eq!(
+            negotiate_value("compress, identity;q=0"),
+            Err(NotAcceptable)
+        );
+        assert_eq!(negotiate_value("gzip, identity;q=0"), gzip);
+        assert_eq!(negotiate_value("*;q=0"), Err(NotAcceptable));
+    }
+
+    #[test]
+    #[cfg(all(feature = "br", feature = "zstd", feature = "deflate"))]
+    fn test_negotiate_features() {
+        assert_eq!(
+            negotiate_value("gzip, deflate, br, zstd"),
+            Ok(Some(ContentCoding::Brotli))
+        );
+        assert_eq!(
+            negotiate_value("gzip;q=0.9, zstd"),
+            Ok(Some(ContentCoding::Zstd))
+        );
+        assert_eq!(negotiate_value("deflate"), Ok(Some(ContentCoding::Deflate)));
+        assert_eq!(
+            negotiate_value("br;q=0.1, gzip;q=0.2"),
+            Ok(Some(ContentCoding::Gzip))
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Errors raised while reading and parsing a request
+use std::io;
+
+use thiserror::Error;
+
+use super::response::StatusCode;
+
+/// Malformed or unacceptable request
+/// Each error is answered with its status code before the connection is closed
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error("malformed request line: {0:?}")]
+    BadRequestLine(String),
+    #[error("malformed header line: {0:?}")]
+    BadHeader(String),
+    #[error("invalid Content-Length: {0:?}")]
+    BadContentLength(String),
+    #[error("invalid Transfer-Encoding: {0}")]
+    BadTransferEncoding(String),
+    #[error("unsupported transfer coding: {0}")]
+    UnsupportedTransferCoding(String),
+    #[error("malformed chunk: {0:?}")]
+    BadChunk(String),
+    #[error("unsupported HTTP version: {0:?}")]
+    InvalidVersion(String),
+    #[error("request-target is longer than {0} bytes")]
+    UriTooLong(usize),
+    #[error("header section is larger than {0} bytes")]
+    HeaderSectionTooLarge(usize),
+    #[error("unsupported content coding: {0}")]
+    UnsupportedContentCoding(String),
+    #[error("malformed {0} body: {1}")]
+    BadContentEncoding(String, String),
+    #[error("decoded body is larger than {0} bytes")]
+    ContentTooLarge(usize),
+}
+
+impl ParseError {
+    /// Status code of the response to the malformed request
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ParseError::BadRequestLine(_)
+            | ParseError::BadHeader(_)
+            | ParseError::BadContentLength(_)
+            | ParseError::BadTransferEncoding(_)
+            | ParseError::BadChunk(_)
+            | ParseError::BadContentEncoding(..) => StatusCode::BadRequest,
+            ParseError::UnsupportedTransferCoding(_) => StatusCode::NotImplemented,
+            ParseError::InvalidVersion(_) => StatusCode::HttpVersionNotSupported,
+            ParseError::UriTooLong(_) => StatusCode::UriTooLong,
+            ParseError::HeaderSectionTooLarge(_) => StatusCode::RequestHeaderFieldsTooLarge,
+            ParseError::UnsupportedContentCoding(_) => StatusCode::UnsupportedMediaType,
+            ParseError::ContentTooLarge(_) => StatusCode::ContentTooLarge,
+        }
+    }
+}
+
+/// Error raised while reading a request from the connection
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
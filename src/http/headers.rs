@@ -0,0 +1,225 @@
+//! Header collection with case-insensitive names
+/// Headers of a request or response
+/// Names are compared case-insensitively, while the original order, spelling
+/// and repeated headers (e.g. multiple `Set-Cookie`) are preserved
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> Self {
+        HeaderMap::default()
+    }
+
+    /// Add a header, keeping the existing headers with the same name
+    pub fn append(&mut self, name: &str, value: &str) {
+        self.entries.push((name.to_string(), value.to_string()));
+    }
+
+    /// Replace the value of the header with the name in place, or add the header when missing
+    /// Other headers with the same name are removed
+    pub fn insert(&mut self, name: &str, value: &str) {
+        let Some(pos) = self.position(name) else {
+            return self.append(name, value);
+        };
+        self.entries[pos].1 = value.to_string();
+        let mut index = 0;
+        self.entries.retain(|(key, _)| {
+            index += 1;
+            index - 1 == pos || !key.eq_ignore_ascii_case(name)
+        });
+    }
+
+    /// Remove all headers with the name
+    pub fn remove(&mut self, name: &str) {
+        self.entries
+            .retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+
+    /// Get the value of the first header with the name
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name).map(|pos| self.entries[pos].1.as_str())
+    }
+
+    /// Get the values of all headers with the name in order
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the elements of a comma-separated list header
+    /// Elements of repeated headers are combined and surrounding whitespace is trimmed
+    pub fn get_list<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.get_all(name)
+            .flat_map(|value| value.split(','))
+            .map(|element| element.trim())
+            .filter(|element| !element.is_empty())
+    }
+
+    /// Check if a comma-separated list header contains the token, ignoring case
+    /// e.g. `Connection: keep-alive, Close` contains `close`
+    pub fn contains_token(&self, name: &str, token: &str) -> bool {
+        self.get_list(name)
+            .any(|element| element.eq_ignore_ascii_case(token))
+    }
+
+    /// Get the elements of a list header with weights and their q-value in thousandths
+    /// e.g. `gzip;q=0.5, br` is `("gzip", 500)` and `("br", 1000)`
+    /// Other parameters are dropped and elements with an invalid q-value are skipped
+    pub fn get_weighted<'a>(&'a self, name: &'a str) -> impl Iterator<Item = (&'a str, u16)> + 'a {
+        self.get_list(name).filter_map(|element| {
+            let mut params = element.split(';');
+            let value = params.next().unwrap_or("").trim();
+            let q = params
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                .map(|(_, q)| parse_qvalue(q.trim()))
+                .unwrap_or(Some(1000))?;
+            Some((value, q))
+        })
+    }
+
+    /// Iterate over all headers in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// `Content-Type` header
+    pub fn content_type(&self) -> Option<&str> {
+        self.get("Content-Type")
+    }
+
+    /// Media type of the `Content-Type` header without parameters, in lowercase
+    /// e.g. `text/plain` for `Text/Plain; charset=utf-8`
+    pub fn media_type(&self) -> Option<String> {
+        self.content_type().map(|value| {
+            value
+                .split(';')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_ascii_lowercase()
+        })
+    }
+
+    /// `User-Agent` header
+    pub fn user_agent(&self) -> Option<&str> {
+        self.get("User-Agent")
+    }
+
+    /// Check if the client asked to close the connection after the response
+    pub fn connection_close(&self) -> bool {
+        self.contains_token("Connection", "close")
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Parse a qvalue (RFC 9110 section 12.4.2) in thousandths
+/// e.g. `0.5` is 500; at most three decimals and no value above 1
+fn parse_qvalue(value: &str) -> Option<u16> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    if frac.len() > 3 || !frac.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let frac = format!("{:0<3}", frac).parse::<u16>().ok()?;
+    match int {
+        "0" => Some(frac),
+        "1" if frac == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_case_insensitive() {
+        let mut headers = HeaderMap::new();
+        headers.append("accept-encoding", "gzip");
+
+        assert_eq!(headers.get("Accept-Encoding"), Some("gzip"));
+        assert_eq!(headers.get("ACCEPT-ENCODING"), Some("gzip"));
+        assert_eq!(headers.get("Content-Type"), None);
+    }
+
+    #[test]
+    fn test_repeated_headers() {
+        let mut headers = HeaderMap::new();
+        headers.append("Accept", "text/html");
+        headers.append("Host", "localhost");
+        headers.append("accept", "application/json, text/plain");
+
+        assert_eq!(headers.get("Accept"), Some("text/html"));
+        assert_eq!(
+            headers.get_all("Accept").collect::<Vec<_>>(),
+            vec!["text/html", "application/json, text/plain"]
+        );
+        assert_eq!(
+            headers.get_list("Accept").collect::<Vec<_>>(),
+            vec!["text/html", "application/json", "text/plain"]
+        );
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut headers = HeaderMap::new();
+        headers.append("ETag", "\"1\"");
+        headers.append("Vary", "Accept");
+        headers.append("etag", "\"2\"");
+        headers.insert("ETag", "W/\"1\"");
+        headers.insert("Content-Encoding", "gzip");
+
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            vec![
+                ("ETag", "W/\"1\""),
+                ("Vary", "Accept"),
+                ("Content-Encoding", "gzip")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_weighted() {
+        let mut headers = HeaderMap::new();
+        headers.append(
+            "Accept-Encoding",
+            "gzip;q=0.5, br ; Q=0, identity;q=1.0, *;q=0.001, x;q=1.5, y;q=0.1234, text/html;level=1",
+        );
+
+        assert_eq!(
+            headers.get_weighted("Accept-Encoding").collect::<Vec<_>>(),
+            vec![
+                ("gzip", 500),
+                ("br", 0),
+                ("identity", 1000),
+                ("*", 1),
+                ("text/html", 1000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let mut headers = HeaderMap::new();
+        headers.append("content-type", "Application/Octet-Stream; foo=bar");
+        headers.append("connection", "keep-alive, Close");
+
+        assert_eq!(
+            headers.media_type().as_deref(),
+            Some("application/octet-stream")
+        );
+        assert!(headers.connection_close());
+    }
+}
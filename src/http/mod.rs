@@ -0,0 +1,9 @@
+pub mod compression;
+pub mod date;
+pub mod encoding;
+pub mod error;
+pub mod headers;
+pub mod reader;
+pub mod request;
+pub mod response;
+pub mod router;
@@ -0,0 +1,520 @@
+//! Incremental reader that splits a byte stream into HTTP requests
+use std::io::{self, Read};
+
+use super::{
+    error::{ParseError, ReadError},
+    request::MAX_URI_LENGTH,
+};
+
+/// Terminator of the request line and header section
+const HEADER_TERMINATOR: &[u8] = b"\r\n\r\n";
+/// Number of bytes requested from the stream per read
+const READ_CHUNK_SIZE: usize = 1024;
+/// Maximum size of the request line and headers, answered with 431 when exceeded
+/// The same limit applies to the trailer section of a chunked body
+pub const MAX_HEADER_SECTION_SIZE: usize = 16 * 1024;
+/// Maximum length of a chunk-size line including chunk extensions
+const MAX_CHUNK_LINE_LENGTH: usize = 4096;
+
+/// Request split from the stream, with the transfer coding of the body removed
+#[derive(Debug)]
+pub struct RawRequest {
+    /// Request line and headers, without the terminating empty line
+    pub head: Vec<u8>,
+    /// Body of the request, decoded when it was sent with `Transfer-Encoding: chunked`
+    pub body: Vec<u8>,
+    /// Trailer fields sent after the last chunk of a chunked body
+    pub trailers: Vec<u8>,
+}
+
+/// How the end of the body is determined
+enum BodyFraming {
+    /// `Content-Length` bytes follow the header section (0 when absent)
+    Length(usize),
+    /// `Transfer-Encoding: chunked`
+    Chunked,
+}
+
+/// Reader that keeps reading from the stream until a whole request is available
+/// Bytes read past the end of a request are kept for the next request
+/// so that pipelined requests on a keep-alive connection are not lost
+pub struct RequestReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> RequestReader<R> {
+    pub fn new(inner: R) -> Self {
+        RequestReader {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Read the next request (request line, headers and body) from the stream
+    /// Returns `Ok(None)` when the peer closed the connection between requests
+    pub fn read_request(&mut self) -> Result<Option<RawRequest>, ReadError> {
+        let Some(header_end) = self.read_header_section()? else {
+            return Ok(None);
+        };
+        let head = self.buffer[..header_end - HEADER_TERMINATOR.len()].to_vec();
+
+        let (body, trailers, request_len) = match body_framing(&head)? {
+            BodyFraming::Length(content_length) => {
+                // Read exactly Content-Length bytes of body
+                let request_len = header_end + content_length;
+                self.fill_to(request_len)?;
+                let body = self.buffer[header_end..request_len].to_vec();
+                (body, Vec::new(), request_len)
+            }
+            BodyFraming::Chunked => self.read_chunked_body(header_end)?,
+        };
+
+        // Hand over the request and keep the leftover bytes for the next one
+        self.buffer.drain(..request_len);
+        Ok(Some(RawRequest {
+            head,
+            body,
+            trailers,
+        }))
+    }
+
+    /// Read until the end of the header section
+    /// Returns the position right after the empty line ending the section
+    fn read_header_section(&mut self) -> Result<Option<usize>, ReadError> {
+        let mut searched = 0;
+        let header_end = loop {
+            if let Some(pos) = find(&self.buffer[searched..], HEADER_TERMINATOR) {
+                break searched + pos + HEADER_TERMINATOR.len();
+            }
+            // Give up on a header section that does not end within the limit
+            if self.buffer.len() > MAX_HEADER_SECTION_SIZE {
+                return Err(header_section_too_large(&self.buffer).into());
+            }
+            // The terminator may be split between two reads
+            searched = self
+                .buffer
+                .len()
+                .saturating_sub(HEADER_TERMINATOR.len() - 1);
+            if self.fill_buffer()? == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed in the middle of the header section",
+                )
+                .into());
+            }
+        };
+        if header_end - HEADER_TERMINATOR.len() > MAX_HEADER_SECTION_SIZE {
+            return Err(header_section_too_large(&self.buffer[..header_end]).into());
+        }
+        Ok(Some(header_end))
+    }
+
+    /// Decode a chunked body starting at `start`
+    /// Returns the decoded body, the trailer section and the end of the request
+    fn read_chunked_body(&mut self, start: usize) -> Result<(Vec<u8>, Vec<u8>, usize), ReadError> {
+        let mut body = Vec::new();
+        let mut pos = start;
+
+        // chunk = chunk-size [ chunk-ext ] CRLF chunk-data CRLF
+        loop {
+            let line_end = self.read_line(pos, MAX_CHUNK_LINE_LENGTH, || {
+                ParseError::BadChunk("chunk-size line is too long".to_string())
+            })?;
+            let chunk_size = parse_chunk_size(&self.buffer[pos..line_end])?;
+            pos = line_end + 2;
+            // last-chunk = 1*"0" [ chunk-ext ] CRLF
+            if chunk_size == 0 {
+                break;
+            }
+
+            let data_end = pos
+                .checked_add(chunk_size)
+                .ok_or_else(|| ParseError::BadChunk(format!("{:x}", chunk_size)))?;
+            self.fill_to(data_end + 2)?;
+            if &self.buffer[data_end..data_end + 2] != b"\r\n" {
+                return Err(
+                    ParseError::BadChunk("chunk-data is not followed by CRLF".to_string()).into(),
+                );
+            }
+            body.extend_from_slice(&self.buffer[pos..data_end]);
+            pos = data_end + 2;
+        }
+
+        // trailer-section = *( field-line CRLF ) CRLF
+        let trailer_start = pos;
+        loop {
+            let limit = (trailer_start + MAX_HEADER_SECTION_SIZE).saturating_sub(pos);
+            let line_end = self.read_line(pos, limit, || {
+                ParseError::HeaderSectionTooLarge(MAX_HEADER_SECTION_SIZE)
+            })?;
+            let is_empty_line = line_end == pos;
+            pos = line_end + 2;
+            if is_empty_line {
+                break;
+            }
+        }
+        let trailers = self.buffer[trailer_start..pos - 2].to_vec();
+
+        Ok((body, trailers, pos))
+    }
+
+    /// Read until a CRLF follows `start`
+    /// Returns the position of the CR, or `too_long` when the line exceeds `limit` bytes
+    fn read_line(
+        &mut self,
+        start: usize,
+        limit: usize,
+        too_long: impl Fn() -> ParseError,
+    ) -> Result<usize, ReadError> {
+        let mut searched = start;
+        loop {
+            if let Some(pos) = find(&self.buffer[searched..], b"\r\n") {
+                return Ok(searched + pos);
+            }
+            if self.buffer.len() - start > limit {
+                return Err(too_long().into());
+            }
+            searched = self.buffer.len().saturating_sub(1).max(start);
+            if self.fill_buffer()? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before the whole body was received",
+                )
+                .into());
+            }
+        }
+    }
+
+    /// Read until the buffer holds at least `len` bytes
+    fn fill_to(&mut self, len: usize) -> Result<(), ReadError> {
+        while self.buffer.len() < len {
+            if self.fill_buffer()? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "connection closed before the whole body was received",
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Append the next chunk of the stream to the buffer
+    /// Returns the number of bytes read, 0 meaning the stream reached EOF
+    fn fill_buffer(&mut self) -> io::Result<usize> {
+        let mut chunk = [0; READ_CHUNK_SIZE];
+        loop {
+            match self.inner.read(&mut chunk) {
+                Ok(read_size) => {
+                    self.buffer.extend_from_slice(&chunk[..read_size]);
+                    return Ok(read_size);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Find the first position of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Error for a header section exceeding the limit
+/// When not even the request line ends within the limit, the request-target is too long
+fn header_section_too_large(header_section: &[u8]) -> ParseError {
+    match find(header_section, b"\r\n") {
+        Some(pos) if pos <= MAX_HEADER_SECTION_SIZE => {
+            ParseError::HeaderSectionTooLarge(MAX_HEADER_SECTION_SIZE)
+        }
+        _ => ParseError::UriTooLong(MAX_URI_LENGTH),
+    }
+}
+
+/// Determine how the body is framed from the header section
+/// A request with neither Content-Length nor Transfer-Encoding has no body
+fn body_framing(head: &[u8]) -> Result<BodyFraming, ParseError> {
+    let head = String::from_utf8_lossy(head);
+    let content_lengths = header_values(&head, "Content-Length");
+    let transfer_encodings = header_values(&head, "Transfer-Encoding");
+
+    if transfer_encodings.is_empty() {
+        return content_length(&content_lengths).map(BodyFraming::Length);
+    }
+    // Both headers make the end of the body ambiguous (request smuggling)
+    if !content_lengths.is_empty() {
+        return Err(ParseError::BadTransferEncoding(
+            "both Transfer-Encoding and Content-Length are present".to_string(),
+        ));
+    }
+
+    let codings: Vec<String> = transfer_encodings
+        .iter()
+        .flat_map(|value| value.split(','))
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty())
+        .collect();
+    // The length cannot be determined unless chunked is the final coding
+    match codings.split_last() {
+        Some((last, [])) if last == "chunked" => Ok(BodyFraming::Chunked),
+        Some((last, others)) if last == "chunked" => {
+            Err(ParseError::UnsupportedTransferCoding(others.join(", ")))
+        }
+        _ => Err(ParseError::BadTransferEncoding(
+            transfer_encodings.join(", "),
+        )),
+    }
+}
+
+/// Get the values of all headers with the name from the header section
+fn header_values<'a>(head: &'a str, name: &str) -> Vec<&'a str> {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+        .collect()
+}
+
+/// Get the body length from the values of the Content-Length headers
+fn content_length(values: &[&str]) -> Result<usize, ParseError> {
+    let Some(value) = values.first() else {
+        return Ok(0);
+    };
+    // Conflicting lengths would make the end of the body ambiguous
+    if values.iter().any(|other| other != value) {
+        return Err(ParseError::BadContentLength(values.join(", ")));
+    }
+    // Only digits are allowed, so `+5` is rejected unlike with `str::parse`
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(ParseError::BadContentLength(value.to_string()));
+    }
+    value
+        .parse::<usize>()
+        .map_err(|_| ParseError::BadContentLength(value.to_string()))
+}
+
+/// Parse the size of a chunk from its chunk-size line
+/// Chunk extensions (`;name=value`) are ignored
+fn parse_chunk_size(line: &[u8]) -> Result<usize, ParseError> {
+    let line = String::from_utf8_lossy(line);
+    let size = line
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim_end_matches([' ', '\t']);
+    if size.is_empty() || !size.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(ParseError::BadChunk(line.to_string()));
+    }
+    usize::from_str_radix(size, 16).map_err(|_| ParseError::BadChunk(line.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reader that returns at most `chunk_size` bytes per read
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk_size: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let size = self.chunk_size.min(buf.len()).min(self.data.len());
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.data = &self.data[size..];
+            Ok(size)
+        }
+    }
+
+    /// Reassemble the request read by the reader
+    fn to_bytes(request: RawRequest) -> Vec<u8> {
+        let mut bytes = request.head;
+        bytes.extend_from_slice(HEADER_TERMINATOR);
+        bytes.extend_from_slice(&request.body);
+        bytes
+    }
+
+    #[test]
+    fn test_read_request_larger_than_chunk() {
+        let body = "a".repeat(5000);
+        let request = format!(
+            "POST /files/foo HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let mut reader = RequestReader::new(request.as_bytes());
+
+        assert_eq!(
+            to_bytes(reader.read_request().unwrap().unwrap()),
+            request.as_bytes()
+        );
+        assert!(reader.read_request().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_request_split_terminator() {
+        let request = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let mut reader = RequestReader::new(ChunkedReader {
+            data: request,
+            chunk_size: 1,
+        });
+
+        assert_eq!(to_bytes(reader.read_request().unwrap().unwrap()), request);
+    }
+
+    #[test]
+    fn test_read_request_keeps_leftover() {
+        let first = "POST /files/foo HTTP/1.1\r\ncontent-length: 3\r\n\r\nabc";
+        let second = "GET / HTTP/1.1\r\n\r\n";
+        let data = format!("{}{}", first, second);
+        let mut reader = RequestReader::new(ChunkedReader {
+            data: data.as_bytes(),
+            chunk_size: 7,
+        });
+
+        assert_eq!(
+            to_bytes(reader.read_request().unwrap().unwrap()),
+            first.as_bytes()
+        );
+        assert_eq!(
+            to_bytes(reader.read_request().unwrap().unwrap()),
+            second.as_bytes()
+        );
+        assert!(reader.read_request().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_request_truncated_body() {
+        let request = b"POST /files/foo HTTP/1.1\r\nContent-Length: 10\r\n\r\nabc";
+        let mut reader = RequestReader::new(&request[..]);
+
+        let err = reader.read_request().unwrap_err();
+        assert!(matches!(err, ReadError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_read_request_header_section_too_large() {
+        let request = format!(
+            "GET / HTTP/1.1\r\nX-Large: {}\r\n\r\n",
+            "a".repeat(MAX_HEADER_SECTION_SIZE)
+        );
+        let mut reader = RequestReader::new(request.as_bytes());
+
+        let err = reader.read_request().unwrap_err();
+        assert!(matches!(
+            err,
+            ReadError::Parse(ParseError::HeaderSectionTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_request_uri_too_long() {
+        let request = format!(
+            "GET /{} HTTP/1.1\r\n\r\n",
+            "a".repeat(MAX_HEADER_SECTION_SIZE)
+        );
+        let mut reader = RequestReader::new(request.as_bytes());
+
+        let err = reader.read_request().unwrap_err();
+        assert!(matches!(err, ReadError::Parse(ParseError::UriTooLong(_))));
+    }
+
+    #[test]
+    fn test_read_request_bad_content_length() {
+        for content_length in ["+3", "abc", "3\r\nContent-Length: 4"] {
+            let request = format!(
+                "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\nabcd",
+                content_length
+            );
+            let mut reader = RequestReader::new(request.as_bytes());
+
+            let err = reader.read_request().unwrap_err();
+            assert!(matches!(
+                err,
+                ReadError::Parse(ParseError::BadContentLength(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_read_request_chunked() {
+        let first = "POST /files/foo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                     4;name=value\r\nWiki\r\n6\r\npedia \r\nE ; a=\"b\"\r\nin \r\n\r\nchunks.\r\n\
+                     0\r\nX-Checksum: abc\r\n\r\n";
+        let second = "GET / HTTP/1.1\r\n\r\n";
+        let data = format!("{}{}", first, second);
+        let mut reader = RequestReader::new(ChunkedReader {
+            data: data.as_bytes(),
+            chunk_size: 3,
+        });
+
+        let request = reader.read_request().unwrap().unwrap();
+        assert_eq!(
+            request.head,
+            b"POST /files/foo HTTP/1.1\r\nTransfer-Encoding: chunked"
+        );
+        assert_eq!(request.body, b"Wikipedia in \r\n\r\nchunks.");
+        assert_eq!(request.trailers, b"X-Checksum: abc\r\n");
+        assert_eq!(
+            to_bytes(reader.read_request().unwrap().unwrap()),
+            second.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_read_request_bad_chunk() {
+        for chunks in [
+            "x\r\nabc\r\n0\r\n\r\n",
+            "3\r\nabcd\r\n0\r\n\r\n",
+            "-1\r\n\r\n",
+        ] {
+            let request = format!(
+                "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+                chunks
+            );
+            let mut reader = RequestReader::new(request.as_bytes());
+
+            let err = reader.read_request().unwrap_err();
+            assert!(
+                matches!(err, ReadError::Parse(ParseError::BadChunk(_))),
+                "{}",
+                chunks
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_request_bad_transfer_encoding() {
+        let requests = [
+            "Transfer-Encoding: gzip\r\n",
+            "Transfer-Encoding: chunked\r\nContent-Length: 3\r\n",
+        ];
+        for headers in requests {
+            let request = format!("POST / HTTP/1.1\r\n{}\r\n0\r\n\r\n", headers);
+            let mut reader = RequestReader::new(request.as_bytes());
+
+            let err = reader.read_request().unwrap_err();
+            assert!(matches!(
+                err,
+                ReadError::Parse(ParseError::BadTransferEncoding(_))
+            ));
+        }
+
+        let request = "POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n0\r\n\r\n";
+        let mut reader = RequestReader::new(request.as_bytes());
+        let err = reader.read_request().unwrap_err();
+        assert!(matches!(
+            err,
+            ReadError::Parse(ParseError::UnsupportedTransferCoding(_))
+        ));
+    }
+}
@@ -0,0 +1,328 @@
+//! Request type parsed from the bytes read by the reader
+use std::io::Read;
+
+use super::{encoding, error::ParseError, headers::HeaderMap, reader::RawRequest};
+
+/// Maximum length of the request-target, answered with 414 when exceeded
+/// RFC 9112 recommends supporting at least 8000 octets
+pub const MAX_URI_LENGTH: usize = 8000;
+
+/// Maximum size of a body after undoing its content codings, answered with 413 when exceeded
+pub const MAX_DECODED_BODY_SIZE: usize = 64 * 1024 * 1024;
+
+pub struct Reqeuest {
+    pub method: RequestMethod,
+    pub uri: String,
+    #[allow(dead_code)]
+    pub version: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Trailer fields of a chunked body, kept apart from the headers
+    #[allow(dead_code)]
+    pub trailers: HeaderMap,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestMethod {
+    GET,
+    HEAD,
+    POST,
+    PUT,
+    DELETE,
+    OPTIONS,
+    PATCH,
+    TRACE,
+    CONNECT,
+    /// Method that is not defined by RFC 9110, e.g. `PROPFIND`
+    Extension(String),
+}
+
+impl RequestMethod {
+    /// Parse the method of the request line
+    /// Methods are case-sensitive, so `get` is an extension method
+    pub fn parse(method: &str) -> Self {
+        match method {
+            "GET" => RequestMethod::GET,
+            "HEAD" => RequestMethod::HEAD,
+            "POST" => RequestMethod::POST,
+            "PUT" => RequestMethod::PUT,
+            "DELETE" => RequestMethod::DELETE,
+            "OPTIONS" => RequestMethod::OPTIONS,
+            "PATCH" => RequestMethod::PATCH,
+            "TRACE" => RequestMethod::TRACE,
+            "CONNECT" => RequestMethod::CONNECT,
+            _ => RequestMethod::Extension(method.to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RequestMethod::GET => "GET",
+            RequestMethod::HEAD => "HEAD",
+            RequestMethod::POST => "POST",
+            RequestMethod::PUT => "PUT",
+            RequestMethod::DELETE => "DELETE",
+            RequestMethod::OPTIONS => "OPTIONS",
+            RequestMethod::PATCH => "PATCH",
+            RequestMethod::TRACE => "TRACE",
+            RequestMethod::CONNECT => "CONNECT",
+            RequestMethod::Extension(method) => method,
+        }
+    }
+}
+
+impl Reqeuest {
+    pub fn new(request: RawRequest) -> Result<Self, ParseError> {
+        // Only the header section is text, the body is kept as raw bytes
+        let req_line_and_headers = String::from_utf8_lossy(&request.head);
+        let mut lines = req_line_and_headers.lines();
+
+        // Request line: method SP request-target SP HTTP-version
+        let line = lines.next().unwrap_or("");
+        let bad_request_line = || ParseError::BadRequestLine(line.to_string());
+        let [method, uri, version] = line.split(' ').collect::<Vec<_>>()[..] else {
+            return Err(bad_request_line());
+        };
+        if method.is_empty() || !method.bytes().all(is_token_char) || uri.is_empty() {
+            return Err(bad_request_line());
+        }
+        if uri.len() > MAX_URI_LENGTH {
+            return Err(ParseError::UriTooLong(MAX_URI_LENGTH));
+        }
+        match version.strip_prefix("HTTP/") {
+            Some("1.0" | "1.1") => {}
+            // Well-formed version that this server does not speak, e.g. HTTP/2.0
+            Some(number) if is_version_number(number) => {
+                return Err(ParseError::InvalidVersion(version.to_string()))
+            }
+            _ => return Err(bad_request_line()),
+        }
+        let method = RequestMethod::parse(method);
+        let uri = uri.to_string();
+        let version = version.to_string();
+
+        let mut headers = parse_fields(lines)?;
+        let trailers = parse_fields(String::from_utf8_lossy(&request.trailers).lines())?;
+        let body = decode_body(&mut headers, request.body, MAX_DECODED_BODY_SIZE)?;
+
+        Ok(Reqeuest {
+            method,
+            uri,
+            version,
+            headers,
+            body,
+            trailers,
+        })
+    }
+}
+
+/// Undo the content codings of the body in the reverse order they were applied
+/// The headers then describe the decoded body, without Content-Encoding
+/// The decoded body is limited to `limit` bytes to defuse decompression bombs
+fn decode_body(
+    headers: &mut HeaderMap,
+    mut body: Vec<u8>,
+    limit: usize,
+) -> Result<Vec<u8>, ParseError> {
+    let codings: Vec<String> = headers
+        .get_list("Content-Encoding")
+        .filter(|coding| !coding.eq_ignore_ascii_case("identity"))
+        .map(str::to_string)
+        .collect();
+    if codings.is_empty() || body.is_empty() {
+        return Ok(body);
+    }
+
+    for coding in codings.iter().rev() {
+        let decoder = encoding::decoder(coding, &body[..])
+            .ok_or_else(|| ParseError::UnsupportedContentCoding(coding.to_string()))?;
+        // Read one byte more than the limit to detect a larger body
+        let mut decoded = Vec::new();
+        decoder
+            .take(limit as u64 + 1)
+            .read_to_end(&mut decoded)
+            .map_err(|e| ParseError::BadContentEncoding(coding.to_string(), e.to_string()))?;
+        if decoded.len() > limit {
+            return Err(ParseError::ContentTooLarge(limit));
+        }
+        body = decoded;
+    }
+
+    headers.remove("Content-Encoding");
+    if headers.get("Content-Length").is_some() {
+        headers.insert("Content-Length", &body.len().to_string());
+    }
+    Ok(body)
+}
+
+/// Parse header (or trailer) lines: field-name ":" OWS field-value OWS
+fn parse_fields<'a>(lines: impl Iterator<Item = &'a str>) -> Result<HeaderMap, ParseError> {
+    let mut fields = HeaderMap::new();
+    for line in lines {
+        let (key, value) = line
+            .split_once(':')
+            .filter(|(key, _)| !key.is_empty() && key.bytes().all(is_token_char))
+            .ok_or_else(|| ParseError::BadHeader(line.to_string()))?;
+        fields.append(key, value.trim_matches([' ', '\t']));
+    }
+    Ok(fields)
+}
+
+/// Check if the byte is allowed in a token (method, header name) by RFC 9110
+fn is_token_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// Check if the string is a version number such as `1.1` or `2`
+fn is_version_number(number: &str) -> bool {
+    !number.is_empty()
+        && number.split('.').count() <= 2
+        && number
+            .split('.')
+            .all(|part| !part.is_empty() && part.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::RequestReader;
+    use flate2::{
+        write::{GzEncoder, ZlibEncoder},
+        Compression,
+    };
+    use std::io::Write;
+
+    /// Read and parse the request from the bytes
+    fn parse(request: &[u8]) -> Result<Reqeuest, ParseError> {
+        let raw = RequestReader::new(request).read_request().unwrap().unwrap();
+        Reqeuest::new(raw)
+    }
+
+    #[test]
+    fn test_new_request() {
+        let request =
+            parse(b"POST /files/foo HTTP/1.1\r\nHost:localhost \r\nContent-Length: 3\r\n\r\nabc")
+                .unwrap();
+
+        assert_eq!(request.method, RequestMethod::POST);
+        assert_eq!(request.uri, "/files/foo");
+        assert_eq!(request.version, "HTTP/1.1");
+        assert_eq!(request.headers.get("host"), Some("localhost"));
+        assert_eq!(request.body, b"abc");
+    }
+
+    #[test]
+    fn test_new_request_trailers() {
+        let request = parse(
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nX-Checksum: 1\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(request.body, b"abc");
+        assert_eq!(request.trailers.get("x-checksum"), Some("1"));
+        assert_eq!(request.headers.get("X-Checksum"), None);
+    }
+
+    #[test]
+    fn test_new_bad_request_line() {
+        for request_line in [
+            "",
+            "GET /",
+            "GET  / HTTP/1.1",
+            "GET / HTTP/1.1 x",
+            "G(T / HTTP/1.1",
+            "GET / HTTQ/1.1",
+        ] {
+            let request = format!("{}\r\n\r\n", request_line);
+            let err = parse(request.as_bytes()).err().unwrap();
+
+            assert!(
+                matches!(err, ParseError::BadRequestLine(_)),
+                "{}",
+                request_line
+            );
+        }
+    }
+
+    #[test]
+    fn test_new_bad_header() {
+        for header in ["Host localhost", ": localhost", "Ho st: localhost"] {
+            let request = format!("GET / HTTP/1.1\r\n{}\r\n\r\n", header);
+            let err = parse(request.as_bytes()).err().unwrap();
+
+            assert!(matches!(err, ParseError::BadHeader(_)), "{}", header);
+        }
+    }
+
+    #[test]
+    fn test_new_invalid_version() {
+        let err = parse(b"GET / HTTP/2.0\r\n\r\n").err().unwrap();
+
+        assert!(matches!(err, ParseError::InvalidVersion(_)));
+        assert_eq!(err.status().code(), 505);
+    }
+
+    #[test]
+    fn test_new_uri_too_long() {
+        let request = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_URI_LENGTH));
+        let err = parse(request.as_bytes()).err().unwrap();
+
+        assert!(matches!(err, ParseError::UriTooLong(_)));
+        assert_eq!(err.status().code(), 414);
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn encoded_headers(content_encoding: &str, len: usize) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.append("Content-Encoding", content_encoding);
+        headers.append("Content-Length", &len.to_string());
+        headers
+    }
+
+    #[test]
+    fn test_decode_body() {
+        let body = gzip(b"hello");
+        let mut headers = encoded_headers("gzip", body.len());
+
+        assert_eq!(decode_body(&mut headers, body, 1024).unwrap(), b"hello");
+        assert_eq!(headers.get("Content-Encoding"), None);
+        assert_eq!(headers.get("Content-Length"), Some("5"));
+
+        // Codings are undone in the reverse order they were applied
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&gzip(b"hello")).unwrap();
+        let body = encoder.finish().unwrap();
+        let mut headers = encoded_headers("X-Gzip, identity, deflate", body.len());
+
+        assert_eq!(decode_body(&mut headers, body, 1024).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_decode_body_errors() {
+        let mut headers = encoded_headers("compress", 5);
+        assert!(matches!(
+            decode_body(&mut headers, b"hello".to_vec(), 1024),
+            Err(ParseError::UnsupportedContentCoding(coding)) if coding == "compress"
+        ));
+
+        let mut headers = encoded_headers("gzip", 5);
+        assert!(matches!(
+            decode_body(&mut headers, b"hello".to_vec(), 1024),
+            Err(ParseError::BadContentEncoding(..))
+        ));
+
+        // A small body that expands beyond the limit
+        let body = gzip(&[0; 4096]);
+        let mut headers = encoded_headers("gzip", body.len());
+        assert!(matches!(
+            decode_body(&mut headers, body, 4095),
+            Err(ParseError::ContentTooLarge(4095))
+        ));
+    }
+}
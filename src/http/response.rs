@@ -0,0 +1,357 @@
+//! Response type and its serializer
+use std::io::{self, prelude::*, BufWriter, Cursor};
+
+use super::{
+    encoding::{self, ContentCoding},
+    headers::HeaderMap,
+};
+
+/// Status codes returned by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusCode {
+    Ok,
+    Created,
+    NoContent,
+    PartialContent,
+    NotModified,
+    BadRequest,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    NotAcceptable,
+    Conflict,
+    PreconditionFailed,
+    ContentTooLarge,
+    UriTooLong,
+    UnsupportedMediaType,
+    RangeNotSatisfiable,
+    RequestHeaderFieldsTooLarge,
+    InternalServerError,
+    NotImplemented,
+    HttpVersionNotSupported,
+}
+
+impl StatusCode {
+    pub fn code(&self) -> u16 {
+        match self {
+            StatusCode::Ok => 200,
+            StatusCode::Created => 201,
+            StatusCode::NoContent => 204,
+            StatusCode::PartialContent => 206,
+            StatusCode::NotModified => 304,
+            StatusCode::BadRequest => 400,
+            StatusCode::Forbidden => 403,
+            StatusCode::NotFound => 404,
+            StatusCode::MethodNotAllowed => 405,
+            StatusCode::NotAcceptable => 406,
+            StatusCode::Conflict => 409,
+            StatusCode::PreconditionFailed => 412,
+            StatusCode::ContentTooLarge => 413,
+            StatusCode::UriTooLong => 414,
+            StatusCode::UnsupportedMediaType => 415,
+            StatusCode::RangeNotSatisfiable => 416,
+            StatusCode::RequestHeaderFieldsTooLarge => 431,
+            StatusCode::InternalServerError => 500,
+            StatusCode::NotImplemented => 501,
+            StatusCode::HttpVersionNotSupported => 505,
+        }
+    }
+
+    pub fn reason_phrase(&self) -> &'static str {
+        match self {
+            StatusCode::Ok => "OK",
+            StatusCode::Created => "Created",
+            StatusCode::NoContent => "No Content",
+            StatusCode::PartialContent => "Partial Content",
+            StatusCode::NotModified => "Not Modified",
+            StatusCode::BadRequest => "Bad Request",
+            StatusCode::Forbidden => "Forbidden",
+            StatusCode::NotFound => "Not Found",
+            StatusCode::MethodNotAllowed => "Method Not Allowed",
+            StatusCode::NotAcceptable => "Not Acceptable",
+            StatusCode::Conflict => "Conflict",
+            StatusCode::PreconditionFailed => "Precondition Failed",
+            StatusCode::ContentTooLarge => "Content Too Large",
+            StatusCode::UriTooLong => "URI Too Long",
+            StatusCode::UnsupportedMediaType => "Unsupported Media Type",
+            StatusCode::RangeNotSatisfiable => "Range Not Satisfiable",
+            StatusCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            StatusCode::InternalServerError => "Internal Server Error",
+            StatusCode::NotImplemented => "Not Implemented",
+            StatusCode::HttpVersionNotSupported => "HTTP Version Not Supported",
+        }
+    }
+}
+
+/// Size of the buffer used to stream a body
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Body of a response
+enum Body {
+    /// Body held in memory
+    Bytes(Vec<u8>),
+    /// Body read from a stream in bounded memory
+    /// Sent with Content-Length when the length is known and chunked otherwise
+    Stream {
+        reader: Box<dyn Read + Send>,
+        len: Option<u64>,
+    },
+}
+
+/// Response to be sent to the client
+/// Framing headers (Content-Length, Transfer-Encoding, Connection) are added by the serializer
+pub struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Body,
+    /// Send the headers of the body but not the body itself (response to HEAD)
+    omit_body: bool,
+}
+
+impl Response {
+    pub fn new(status: StatusCode) -> Self {
+        Response {
+            status,
+            headers: HeaderMap::new(),
+            body: Body::Bytes(Vec::new()),
+            omit_body: false,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Length of the body when it is known before sending it
+    pub fn body_len(&self) -> Option<u64> {
+        match &self.body {
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Stream { len, .. } => *len,
+        }
+    }
+
+    /// Add a header to the response
+    /// Headers are sent in the order they are added
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Set the body of the response
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Body::Bytes(body.into());
+        self
+    }
+
+    /// Stream the body from the reader instead of holding it in memory
+    /// `len` is sent as Content-Length; without it the body is sent with chunked encoding
+    pub fn stream(mut self, reader: impl Read + Send + 'static, len: Option<u64>) -> Self {
+        self.body = Body::Stream {
+            reader: Box::new(reader),
+            len,
+        };
+        self
+    }
+
+    /// Compress the body with the content coding and add Content-Encoding
+    /// Streamed bodies are compressed while they are sent, with chunked encoding
+    /// A strong ETag becomes weak, as it identifies the bytes of the uncompressed body
+    pub fn encode(mut self, coding: ContentCoding, level: u32) -> Self {
+        self.body = match self.body {
+            Body::Bytes(bytes) => {
+                let mut encoded = Vec::new();
+                encoding::encoder(coding, Cursor::new(bytes), level)
+                    .read_to_end(&mut encoded)
+                    .expect("compressing a body in memory cannot fail");
+                Body::Bytes(encoded)
+            }
+            Body::Stream { reader, .. } => Body::Stream {
+                reader: encoding::encoder(coding, reader, level),
+                len: None,
+            },
+        };
+        if let Some(etag) = self
+            .headers
+            .get("ETag")
+            .filter(|etag| !etag.starts_with("W/"))
+        {
+            let weak = format!("W/{}", etag);
+            self.headers.insert("ETag", &weak);
+        }
+        self.header("Content-Encoding", coding.as_str())
+    }
+
+    /// Drop the body from the serialized response while keeping its Content-Length
+    /// Used to answer HEAD with the same headers as GET
+    pub fn omit_body(mut self) -> Self {
+        self.omit_body = true;
+        self
+    }
+
+    /// Serialize the status line and headers including the framing headers
+    /// `close_connection` adds `Connection: close` to tell the client the connection ends here
+    fn head(&self, close_connection: bool) -> String {
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status.code(),
+            self.status.reason_phrase()
+        );
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        match &self.body {
+            Body::Bytes(bytes) if bytes.is_empty() => {}
+            Body::Bytes(bytes) => head.push_str(&format!("Content-Length: {}\r\n", bytes.len())),
+            Body::Stream { len: Some(len), .. } => {
+                head.push_str(&format!("Content-Length: {}\r\n", len))
+            }
+            Body::Stream { len: None, .. } => head.push_str("Transfer-Encoding: chunked\r\n"),
+        }
+        if close_connection {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+        head
+    }
+
+    /// Write the response to the stream
+    /// Small responses are sent with a single write; streamed bodies in bounded chunks
+    pub fn write_to<W: Write>(self, writer: W, close_connection: bool) -> io::Result<()> {
+        let mut writer = BufWriter::with_capacity(STREAM_BUFFER_SIZE, writer);
+        writer.write_all(self.head(close_connection).as_bytes())?;
+
+        if !self.omit_body {
+            match self.body {
+                Body::Bytes(bytes) => writer.write_all(&bytes)?,
+                Body::Stream {
+                    reader,
+                    len: Some(len),
+                } => {
+                    // The client relies on Content-Length, so a short stream is an error
+                    let copied = io::copy(&mut reader.take(len), &mut writer)?;
+                    if copied < len {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            format!("body ended after {} of {} bytes", copied, len),
+                        ));
+                    }
+                }
+                Body::Stream { reader, len: None } => write_chunked(reader, &mut writer)?,
+            }
+        }
+        writer.flush()
+    }
+
+    /// Serialize the whole response into bytes
+    #[cfg(test)]
+    pub fn into_bytes(self, close_connection: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes, close_connection).unwrap();
+        bytes
+    }
+}
+
+/// Write the stream with chunked transfer coding
+/// Each read becomes one chunk, followed by the last chunk without trailers
+fn write_chunked<W: Write>(mut reader: impl Read, writer: &mut W) -> io::Result<()> {
+    let mut buffer = vec![0; STREAM_BUFFER_SIZE];
+    loop {
+        let read_size = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read_size) => read_size,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        write!(writer, "{:x}\r\n", read_size)?;
+        writer.write_all(&buffer[..read_size])?;
+        writer.write_all(b"\r\n")?;
+    }
+    writer.write_all(b"0\r\n\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_into_bytes_without_body() {
+        let response = Response::new(StatusCode::NotFound);
+
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 404 Not Found\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_into_bytes_with_body() {
+        let response = Response::new(StatusCode::Ok)
+            .header("Content-Type", "text/plain")
+            .body("abc");
+
+        assert_eq!(
+            response.into_bytes(true),
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 3\r\nConnection: close\r\n\r\nabc"
+        );
+    }
+
+    #[test]
+    fn test_into_bytes_omit_body() {
+        let response = Response::new(StatusCode::Ok).body("abc").omit_body();
+
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_into_bytes_stream() {
+        let response = Response::new(StatusCode::Ok).stream(&b"abcdef"[..], Some(6));
+
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nabcdef"
+        );
+    }
+
+    #[test]
+    fn test_into_bytes_stream_chunked() {
+        // Reader that returns 4 bytes per read
+        let reader = (&b"abcd"[..]).chain(&b"ef"[..]);
+        let response = Response::new(StatusCode::Ok).stream(reader, None);
+
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nabcd\r\n2\r\nef\r\n0\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let response = Response::new(StatusCode::Ok)
+            .header("ETag", "\"1\"")
+            .stream(&b"abc"[..], Some(3))
+            .encode(ContentCoding::Gzip, 6);
+
+        assert_eq!(response.headers().get("ETag"), Some("W/\"1\""));
+        assert_eq!(response.headers().get("Content-Encoding"), Some("gzip"));
+        assert_eq!(response.body_len(), None);
+        let bytes = response.into_bytes(false);
+        assert!(bytes.starts_with(
+            b"HTTP/1.1 200 OK\r\nETag: W/\"1\"\r\nContent-Encoding: gzip\r\nTransfer-Encoding: chunked\r\n\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_write_to_short_stream() {
+        let response = Response::new(StatusCode::Ok).stream(&b"abc"[..], Some(6));
+
+        let err = response.write_to(Vec::new(), false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
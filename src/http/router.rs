@@ -0,0 +1,306 @@
+//! Router that dispatches requests to handlers by method and path pattern
+use super::{
+    compression::CompressionConfig,
+    request::{Reqeuest, RequestMethod},
+    response::{Response, StatusCode},
+};
+
+/// Function that creates the response for a matched route
+pub type Handler = Box<dyn Fn(&Reqeuest, &Params) -> Response + Send + Sync>;
+
+/// Path parameters captured by `:name` and `*name` segments
+#[derive(Debug, Default)]
+pub struct Params {
+    values: Vec<(String, String)>,
+}
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Segment of a route pattern
+enum Segment {
+    /// Segment that must match exactly, e.g. `files`
+    Static(String),
+    /// Single non-empty segment, e.g. `:name`
+    Param(String),
+    /// Rest of the path including slashes, e.g. `*rest`
+    /// Only allowed as the last segment
+    CatchAll(String),
+}
+
+struct Route {
+    method: RequestMethod,
+    pattern: Vec<Segment>,
+    handler: Handler,
+}
+
+impl Route {
+    /// Match the path against the pattern and capture the parameters
+    fn matches(&self, path: &str) -> Option<Params> {
+        let mut params = Params::default();
+        let mut segments = path.trim_start_matches('/').split('/');
+
+        for (i, segment) in self.pattern.iter().enumerate() {
+            match segment {
+                Segment::CatchAll(name) => {
+                    // Everything after the previous segments belongs to the catch-all
+                    let rest: Vec<&str> = segments.collect();
+                    params.values.push((name.clone(), rest.join("/")));
+                    debug_assert_eq!(i, self.pattern.len() - 1);
+                    return Some(params);
+                }
+                Segment::Static(expected) => {
+                    if segments.next()? != expected {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    let value = segments.next().filter(|value| !value.is_empty())?;
+                    params.values.push((name.clone(), value.to_string()));
+                }
+            }
+        }
+
+        // The whole path must be consumed
+        match segments.next() {
+            Some(_) => None,
+            None => Some(params),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    /// Compression applied to the responses of the handlers
+    compression: Option<CompressionConfig>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router::default()
+    }
+
+    /// Register a handler for the method and pattern
+    /// The pattern is split by `/`; `:name` captures one segment and `*name` the rest of the path
+    /// # Panics
+    /// Panics if a `*name` segment is not the last segment of the pattern
+    pub fn route<F>(mut self, method: RequestMethod, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(&Reqeuest, &Params) -> Response + Send + Sync + 'static,
+    {
+        let pattern: Vec<Segment> = pattern
+            .trim_start_matches('/')
+            .split('/')
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::CatchAll(name.to_string())
+                } else {
+                    Segment::Static(segment.to_string())
+                }
+            })
+            .collect();
+        assert!(
+            pattern
+                .iter()
+                .rev()
+                .skip(1)
+                .all(|segment| !matches!(segment, Segment::CatchAll(_))),
+            "Catch-all segment must be the last segment of the pattern"
+        );
+
+        self.routes.push(Route {
+            method,
+            pattern,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Compress the responses of the handlers when the client accepts it
+    pub fn compression(mut self, compression: CompressionConfig) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Dispatch the request to the first route matching its method and path
+    /// HEAD falls back to the GET route and OPTIONS is answered with the allowed methods
+    /// Returns 501 for methods no route implements, 404 when no path matches
+    /// and 405 with an Allow header when only the path matches
+    pub fn handle(&self, request: &Reqeuest) -> Response {
+        let response = self.dispatch(request);
+        match &self.compression {
+            Some(compression) => compression.apply(request, response),
+            None => response,
+        }
+    }
+
+    fn dispatch(&self, request: &Reqeuest) -> Response {
+        if !self.implements(&request.method) {
+            return Response::new(StatusCode::NotImplemented);
+        }
+
+        let path = request.uri.as_str();
+        let matched: Vec<(&Route, Params)> = self
+            .routes
+            .iter()
+            .filter_map(|route| route.matches(path).map(|params| (route, params)))
+            .collect();
+        if matched.is_empty() {
+            return Response::new(StatusCode::NotFound);
+        }
+
+        if let Some((route, params)) = matched
+            .iter()
+            .find(|(route, _)| route.method == request.method)
+        {
+            return (route.handler)(request, params);
+        }
+        if request.method == RequestMethod::HEAD {
+            if let Some((route, params)) = matched
+                .iter()
+                .find(|(route, _)| route.method == RequestMethod::GET)
+            {
+                return (route.handler)(request, params).omit_body();
+            }
+        }
+
+        // Collect the methods that are allowed for the path
+        let mut allowed_methods: Vec<&RequestMethod> = Vec::new();
+        for (route, _) in &matched {
+            if !allowed_methods.contains(&&route.method) {
+                allowed_methods.push(&route.method);
+            }
+        }
+        if allowed_methods.contains(&&RequestMethod::GET)
+            && !allowed_methods.contains(&&RequestMethod::HEAD)
+        {
+            allowed_methods.push(&RequestMethod::HEAD);
+        }
+        if !allowed_methods.contains(&&RequestMethod::OPTIONS) {
+            allowed_methods.push(&RequestMethod::OPTIONS);
+        }
+        let allow = allowed_methods
+            .iter()
+            .map(|method| method.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let status = if request.method == RequestMethod::OPTIONS {
+            StatusCode::Ok
+        } else {
+            StatusCode::MethodNotAllowed
+        };
+        Response::new(status).header("Allow", &allow)
+    }
+
+    /// Check if any route can answer the method
+    fn implements(&self, method: &RequestMethod) -> bool {
+        match method {
+            RequestMethod::OPTIONS => true,
+            RequestMethod::HEAD => self.routes.iter().any(|route| {
+                route.method == RequestMethod::HEAD || route.method == RequestMethod::GET
+            }),
+            method => self.routes.iter().any(|route| &route.method == method),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::reader::RawRequest;
+
+    fn request(method: &str, uri: &str) -> Reqeuest {
+        Reqeuest::new(RawRequest {
+            head: format!("{} {} HTTP/1.1\r\nHost: localhost", method, uri).into_bytes(),
+            body: Vec::new(),
+            trailers: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    fn echo_param(name: &'static str) -> impl Fn(&Reqeuest, &Params) -> Response {
+        move |_, params| Response::new(StatusCode::Ok).body(params.get(name).unwrap_or(""))
+    }
+
+    #[test]
+    fn test_route_params() {
+        let router = Router::new()
+            .route(RequestMethod::GET, "/files/:name", echo_param("name"))
+            .route(RequestMethod::GET, "/echo/*rest", echo_param("rest"));
+
+        let response = router.handle(&request("GET", "/files/foo"));
+        assert!(response.into_bytes(false).ends_with(b"\r\n\r\nfoo"));
+        let response = router.handle(&request("GET", "/echo/a/b/c"));
+        assert!(response.into_bytes(false).ends_with(b"\r\n\r\na/b/c"));
+    }
+
+    #[test]
+    fn test_route_not_found() {
+        let router = Router::new()
+            .route(RequestMethod::GET, "/user-agent", echo_param("name"))
+            .route(RequestMethod::GET, "/files/:name", echo_param("name"));
+
+        for uri in ["/user-agentXYZ", "/filesystem", "/files/", "/files/a/b"] {
+            let response = router.handle(&request("GET", uri));
+            assert_eq!(
+                response.into_bytes(false),
+                b"HTTP/1.1 404 Not Found\r\n\r\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_route_method_not_allowed() {
+        let router = Router::new()
+            .route(RequestMethod::GET, "/files/:name", echo_param("name"))
+            .route(RequestMethod::POST, "/files/:name", echo_param("name"))
+            .route(RequestMethod::GET, "/", echo_param("name"));
+
+        let response = router.handle(&request("POST", "/"));
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD, OPTIONS\r\n\r\n"
+        );
+        let response = router.handle(&request("OPTIONS", "/files/foo"));
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 200 OK\r\nAllow: GET, POST, HEAD, OPTIONS\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_route_head() {
+        let router = Router::new().route(RequestMethod::GET, "/files/:name", echo_param("name"));
+
+        let response = router.handle(&request("HEAD", "/files/foo"));
+        assert_eq!(
+            response.into_bytes(false),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_route_not_implemented() {
+        let router = Router::new()
+            .route(RequestMethod::GET, "/files/:name", echo_param("name"))
+            .route(RequestMethod::POST, "/files/:name", echo_param("name"));
+
+        for method in ["PATCH", "PROPFIND", "get"] {
+            let response = router.handle(&request(method, "/files/foo"));
+            assert_eq!(
+                response.into_bytes(false),
+                b"HTTP/1.1 501 Not Implemented\r\n\r\n"
+            );
+        }
+    }
+}
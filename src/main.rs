@@ -1,84 +1,44 @@
 #[allow(unused_imports)]
 use std::{
-    collections::HashMap,
-    env, fs,
+    env,
     io::prelude::*,
     net::{TcpListener, TcpStream},
+    sync::Arc,
 };
 
-use flate2::{write::GzEncoder, Compression};
-
+mod config;
+mod handlers;
+mod http;
 mod shared;
+use config::Config;
+use handlers::files::{FilesConfig, MimeTypes};
+use http::{
+    error::ReadError,
+    reader::RequestReader,
+    request::{Reqeuest, RequestMethod},
+    response::Response,
+    router::Router,
+};
 use shared::thread_pool::ThreadPool;
 
-struct Reqeuest {
-    method: RequestMethod,
-    uri: String,
-    version: String,
-    headers: Vec<HashMap<String, String>>,
-    body: String,
-}
-
-enum RequestMethod {
-    GET,
-    POST,
-}
-
-impl Reqeuest {
-    fn new(request: &[u8]) -> Self {
-        let request_str = String::from_utf8_lossy(request);
-        // Split the request into body and (headers + request line)
-        let mut request_info = request_str.split("\r\n\r\n");
-
-        let req_line_and_headers = request_info.next().unwrap();
-        let mut lines = req_line_and_headers.lines();
-
-        // Request line
-        let line = lines.next().unwrap();
-        let mut request_line = line.split_whitespace();
-        let method = request_line.next().unwrap();
-        let method = match method {
-            "GET" => RequestMethod::GET,
-            "POST" => RequestMethod::POST,
-            _ => panic!("Unsupported request method: {}", method),
-        };
-        let uri = request_line.next().unwrap().to_string();
-        let version = request_line.next().unwrap().to_string();
-
-        // Headers
-        let mut headers = Vec::new();
-        while let Some(line) = lines.next() {
-            let mut header = HashMap::new();
-            let (key, value) = line.split_once(": ").unwrap();
-            header.insert(key.to_string(), value.to_string());
-            headers.push(header);
-        }
-
-        // Body
-        let body = request_info.next().unwrap_or("").to_string();
-
-        Reqeuest {
-            method,
-            uri,
-            version,
-            headers,
-            body,
-        }
-    }
-}
-
 fn main() {
     // You can use print statements as follows for debugging, they'll be visible when running tests.
     println!("Logs from your program will appear here!");
 
+    // Set directory for response files and other options
+    let config = Config::from_args(env::args().skip(1));
+    // The router is shared among all connections
+    let router = Arc::new(create_router(&config));
+
     let listener = TcpListener::bind("127.0.0.1:4221").unwrap();
     let pool = ThreadPool::new(5);
 
     for stream in listener.incoming() {
         match stream {
             Ok(stream) => {
-                pool.execute(|| {
-                    handle_connection(stream);
+                let router = Arc::clone(&router);
+                pool.execute(move || {
+                    handle_connection(stream, &router);
                 });
             }
             Err(e) => {
@@ -88,262 +48,112 @@ fn main() {
     }
 }
 
-fn handle_connection(stream: TcpStream) {
-    println!("accepted new connection");
+/// Register the routes of the server
+/// `/files` routes are only available when a directory is provided
+/// Responses are compressed unless disabled
+fn create_router(config: &Config) -> Router {
+    let mut router = Router::new()
+        .route(RequestMethod::GET, "/", handlers::root)
+        .route(RequestMethod::GET, "/echo/:text", handlers::echo)
+        .route(RequestMethod::GET, "/user-agent", handlers::user_agent);
+    if !config.no_compression {
+        router = router.compression(config.compression.clone());
+    }
 
-    // Set directory for response files
-    let mut args = env::args().skip(1);
-    let res_file_dir = match args.next() {
-        Some(dir_option) if dir_option == "--directory" => args.next(),
-        _ => None,
+    let Some(directory) = &config.directory else {
+        return router;
     };
+    let mut mime_types = MimeTypes::default();
+    if let Some(path) = &config.mime_types {
+        if let Err(e) = mime_types.load(path) {
+            println!("warning: cannot read {}: {}", path.display(), e);
+        }
+    }
+    let files = Arc::new(FilesConfig {
+        root: directory.clone(),
+        create_dirs: config.create_dirs,
+        listing: !config.no_listing,
+        weak_etags: config.weak_etags,
+        mime_types,
+        sniff: config.sniff,
+    });
+    let get_files = Arc::clone(&files);
+    let post_files = Arc::clone(&files);
+    let put_files = Arc::clone(&files);
+    router
+        .route(
+            RequestMethod::GET,
+            "/files/*path",
+            move |request, params| handlers::files::get_file(request, params, &get_files),
+        )
+        .route(
+            RequestMethod::POST,
+            "/files/*path",
+            move |request, params| handlers::files::post_file(request, params, &post_files),
+        )
+        .route(
+            RequestMethod::PUT,
+            "/files/*path",
+            move |request, params| handlers::files::put_file(request, params, &put_files),
+        )
+        .route(
+            RequestMethod::DELETE,
+            "/files/*path",
+            move |request, params| handlers::files::delete_file(request, params, &files),
+        )
+}
 
+fn handle_connection(stream: TcpStream, router: &Router) {
+    println!("accepted new connection");
+
+    // Leftover bytes of a request are kept by the reader for the next request
+    let mut reader = RequestReader::new(&stream);
+    // Read the Request until the client closes the connection
     loop {
-        // Read the Request
-        let request = read_request(&stream);
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(ReadError::Parse(e)) => {
+                println!("error: {}", e);
+                // The rest of the stream cannot be framed after a malformed request
+                if let Err(e) = Response::new(e.status()).write_to(&stream, true) {
+                    println!("error: {}", e);
+                }
+                break;
+            }
+            Err(ReadError::Io(e)) => {
+                println!("error: {}", e);
+                break;
+            }
+        };
 
         // Create the Response
-        let finished_connection = create_response(&stream, request, &res_file_dir);
+        let finished_connection = create_response(&stream, request, router);
         if finished_connection {
             break;
         }
     }
 }
 
-fn read_request(mut stream: &TcpStream) -> Reqeuest {
-    let mut buffer = [0; 1024];
-    stream.read(&mut buffer).unwrap();
-    println!("Request: {}", String::from_utf8_lossy(&buffer[..]));
-
-    Reqeuest::new(&buffer)
+/// Read the next request from the connection
+/// Returns `Ok(None)` when the client closed the connection
+fn read_request<R: Read>(reader: &mut RequestReader<R>) -> Result<Option<Reqeuest>, ReadError> {
+    let Some(raw) = reader.read_request()? else {
+        return Ok(None);
+    };
+    println!("Request: {}", String::from_utf8_lossy(&raw.head));
+    Ok(Some(Reqeuest::new(raw)?))
 }
 
-fn create_response(
-    mut stream: &TcpStream,
-    request: Reqeuest,
-    res_file_dir: &Option<String>,
-) -> bool {
+fn create_response(mut stream: &TcpStream, request: Reqeuest, router: &Router) -> bool {
     // Check if the connection should be closed
-    let finished_connection = request
-        .headers
-        .iter()
-        .find(|header| header.contains_key("Connection"))
-        .and_then(|header| header.get("Connection").cloned())
-        .unwrap_or("".to_string())
-        == "close";
-    let path = request.uri.as_str();
-    match path {
-        "/" => {
-            stream.write("HTTP/1.1 200 OK".as_bytes()).unwrap();
-            if finished_connection {
-                stream.write("\r\nConnection: close".as_bytes()).unwrap();
-            }
-            stream.write("\r\n\r\n".as_bytes()).unwrap();
-        }
-        _ if path.starts_with("/echo/") => {
-            // Get the subpath after /echo/
-            let mut iter = path.split("/");
-            let sub_path = iter.nth(2).unwrap();
-
-            // Get the Accept-Encoding header
-            let accept_encodings = request
-                .headers
-                .iter()
-                .find(|header| header.contains_key("Accept-Encoding"))
-                .and_then(|header| header.get("Accept-Encoding").cloned())
-                .unwrap_or("".to_string());
-
-            // Check if the Accept-Encoding header contains gzip
-            if accept_encodings
-                .split(", ")
-                .any(|encoding| encoding == "gzip")
-            {
-                // gzip encoding
-                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-                encoder.write_all(sub_path.as_bytes()).unwrap();
-                let compress_data = encoder.finish().unwrap();
-
-                // If it does, return the response with gzip encoding
-                let response = if finished_connection {
-                    format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
-                    compress_data.len()
-                    )
-                } else {
-                    format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
-                    compress_data.len()
-                    )
-                };
-                stream.write(response.as_bytes()).unwrap();
-                stream.write(&compress_data).unwrap();
-            } else {
-                // If it doesn't, return the response without gzip encoding
-                let response = if finished_connection {
-                    format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                    sub_path.len(),
-                    sub_path
-                    )
-                } else {
-                    format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
-                    sub_path.len(),
-                    sub_path
-                    )
-                };
-                stream.write(response.as_bytes()).unwrap();
-            }
-        }
-        _ if path.starts_with("/user-agent") => {
-            let user_agent = request
-                .headers
-                .iter()
-                .find(|header| header.contains_key("User-Agent"))
-                .and_then(|header| header.get("User-Agent").cloned())
-                .unwrap_or("".to_string());
-            let response = if finished_connection {
-                format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                    user_agent.len(),
-                    user_agent
-                )
-            } else {
-                format!(
-                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
-                    user_agent.len(),
-                    user_agent
-                )
-            };
-            stream.write(response.as_bytes()).unwrap();
-        }
-        _ if path.starts_with("/files") => {
-            match res_file_dir {
-                // Check if the directory is provided
-                Some(dir) => {
-                    match request.method {
-                        RequestMethod::GET => {
-                            // Get the filename and contents of file
-                            let mut iter = path.split("/");
-                            let file_name = iter.nth(2).unwrap();
-                            let file_path = format!("{}/{}", dir, file_name);
-                            let file_content = fs::read(file_path);
-                            match file_content {
-                                Ok(content) => {
-                                    let response = if finished_connection {
-                                        format!(
-                                                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
-                                                content.len(),
-                                                String::from_utf8_lossy(&content)
-                                            )
-                                    } else {
-                                        format!(
-                                                "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n{}",
-                                                content.len(),
-                                                String::from_utf8_lossy(&content)
-                                            )
-                                    };
-                                    stream.write(response.as_bytes()).unwrap();
-                                }
-                                Err(_) => {
-                                    if finished_connection {
-                                        stream
-                                            .write("HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n".as_bytes())
-                                            .unwrap();
-                                    } else {
-                                        stream
-                                            .write("HTTP/1.1 404 Not Found\r\n\r\n".as_bytes())
-                                            .unwrap();
-                                    }
-                                }
-                            }
-                        }
-                        RequestMethod::POST => {
-                            let content_type = request
-                                .headers
-                                .iter()
-                                .find(|header| header.contains_key("Content-Type"))
-                                .and_then(|header| header.get("Content-Type").cloned())
-                                .unwrap_or("".to_string());
-
-                            // Check if the content type is application/octet-stream
-                            if content_type == "application/octet-stream" {
-                                // Get the Content-Length header of the request
-                                let content_length = request
-                                    .headers
-                                    .iter()
-                                    .find(|header| header.contains_key("Content-Length"))
-                                    .and_then(|header| header.get("Content-Length").cloned())
-                                    .unwrap_or("0".to_string())
-                                    .parse::<usize>()
-                                    .unwrap_or(0);
-
-                                // Get the filename
-                                let mut iter = path.split("/");
-                                let file_name = iter.nth(2).unwrap();
-                                let file_path = format!("{}/{}", dir, file_name);
-                                //Create the file and write the contents
-                                let mut file = fs::File::create(file_path).unwrap();
-                                file.write_all(&request.body.as_bytes()[..content_length])
-                                    .unwrap();
-
-                                if finished_connection {
-                                    stream
-                                        .write(
-                                            "HTTP/1.1 201 Created\r\nConnection: close\r\n\r\n"
-                                                .as_bytes(),
-                                        )
-                                        .unwrap();
-                                } else {
-                                    stream
-                                        .write("HTTP/1.1 201 Created\r\n\r\n".as_bytes())
-                                        .unwrap();
-                                }
-                            } else {
-                                // If the content type is not application/octet-stream, return 415
-                                if finished_connection {
-                                    stream
-                                        .write(
-                                            "HTTP/1.1 415 Unsupported Media Type\r\nConnection: close\r\n\r\n"
-                                                .as_bytes(),
-                                        )
-                                        .unwrap();
-                                } else {
-                                    stream
-                                        .write(
-                                            "HTTP/1.1 415 Unsupported Media Type\r\n\r\n"
-                                                .as_bytes(),
-                                        )
-                                        .unwrap();
-                                }
-                            }
-                        }
-                    }
-                }
-                None => {
-                    if finished_connection {
-                        stream
-                            .write("HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n".as_bytes())
-                            .unwrap();
-                    } else {
-                        stream
-                            .write("HTTP/1.1 404 Not Found\r\n\r\n".as_bytes())
-                            .unwrap();
-                    }
-                }
-            }
-        }
-        _ => {
-            if finished_connection {
-                stream
-                    .write("HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n".as_bytes())
-                    .unwrap();
-            } else {
-                stream
-                    .write("HTTP/1.1 404 Not Found\r\n\r\n".as_bytes())
-                    .unwrap();
-            }
-        }
+    let finished_connection = request.headers.connection_close();
+
+    let response = router.handle(&request);
+    // A response that could not be written completely leaves the connection unusable
+    if let Err(e) = response.write_to(&mut stream, finished_connection) {
+        println!("error: {}", e);
+        return true;
     }
 
     finished_connection
@@ -352,7 +162,8 @@ fn create_response(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{thread, vec};
+    use flate2::{write::GzEncoder, Compression};
+    use std::{fs, thread, vec};
 
     #[test]
     fn test_handle_connection_success() {
@@ -362,14 +173,14 @@ mod tests {
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                handle_connection(stream.0, &create_router(&Config::default()));
             }
         });
 
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
         let request = "GET / HTTP/1.1\r\nHost: localhost:4221\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
@@ -386,14 +197,14 @@ mod tests {
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                handle_connection(stream.0, &create_router(&Config::default()));
             }
         });
 
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
         let request = "GET /abcdefg HTTP/1.1\r\nHost: localhost\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
@@ -410,14 +221,14 @@ mod tests {
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                handle_connection(stream.0, &create_router(&Config::default()));
             }
         });
 
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
         let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
@@ -425,7 +236,7 @@ mod tests {
 
         assert_eq!(
             response_str,
-            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 3\r\n\r\nabc"
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 3\r\n\r\nabc"
         );
     }
 
@@ -437,7 +248,7 @@ mod tests {
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                handle_connection(stream.0, &create_router(&Config::default()));
             }
         });
 
@@ -445,7 +256,7 @@ mod tests {
         let mut client_stream = TcpStream::connect(addr).unwrap();
         let request =
             "GET /user-agent HTTP/1.1\r\nHost: localhost\r\nUser-Agent: foobar/1.2.3\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
@@ -453,7 +264,7 @@ mod tests {
 
         assert_eq!(
             response_str,
-            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 12\r\n\r\nfoobar/1.2.3"
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 12\r\n\r\nfoobar/1.2.3"
         );
     }
 
@@ -465,8 +276,9 @@ mod tests {
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                let request = read_request(&stream.0);
-                create_response(&stream.0, request, &Option::Some(String::from("/tmp")));
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
             }
         });
 
@@ -477,20 +289,91 @@ mod tests {
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
         let request = "GET /files/foo HTTP/1.1\r\nHost: localhost\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
-        let response_str = String::from_utf8_lossy(&response[..read_size]);
+        let response = strip_validators(&response[..read_size]);
+        let response_str = String::from_utf8_lossy(&response);
         // Clean up the file
         fs::remove_file("/tmp/foo").unwrap();
 
         assert_eq!(
             response_str,
-            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 13\r\n\r\nHello, World!"
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nX-Content-Type-Options: nosniff\r\nAccept-Ranges: bytes\r\nContent-Length: 13\r\n\r\nHello, World!"
         );
     }
 
+    #[test]
+    fn test_handle_connection_files_binary() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
+            }
+        });
+
+        // Create a file that is not valid UTF-8 in the directory
+        let content: Vec<u8> = vec![0x1f, 0x8b, 0x08, 0x00, 0xff, 0xfe, 0x80, 0x00];
+        fs::write("/tmp/foo_binary", &content).unwrap();
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /files/foo_binary HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = Vec::new();
+        client_stream.read_to_end(&mut response).unwrap();
+        // Clean up the file
+        fs::remove_file("/tmp/foo_binary").unwrap();
+
+        let mut expected = "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nX-Content-Type-Options: nosniff\r\nAccept-Ranges: bytes\r\nContent-Length: 8\r\n\r\n"
+            .as_bytes()
+            .to_vec();
+        expected.extend_from_slice(&content);
+        assert_eq!(strip_validators(&response), expected);
+    }
+
+    #[test]
+    fn test_handle_connection_files_large() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
+            }
+        });
+
+        // Create a file larger than the stream buffer in the directory
+        let content: Vec<u8> = (0..300_000).map(|i| (i % 251) as u8).collect();
+        fs::write("/tmp/foo_large", &content).unwrap();
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /files/foo_large HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = Vec::new();
+        client_stream.read_to_end(&mut response).unwrap();
+        // Clean up the file
+        fs::remove_file("/tmp/foo_large").unwrap();
+
+        let mut expected = "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nX-Content-Type-Options: nosniff\r\nAccept-Ranges: bytes\r\nContent-Length: 300000\r\n\r\n"
+            .as_bytes()
+            .to_vec();
+        expected.extend_from_slice(&content);
+        assert!(strip_validators(&response) == expected);
+    }
+
     #[test]
     fn test_handle_connection_files_404() {
         let listener = start_local_server();
@@ -499,15 +382,16 @@ mod tests {
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                let request = read_request(&stream.0);
-                create_response(&stream.0, request, &Option::Some(String::from("/tmp")));
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
             }
         });
 
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
         let request = "GET /files/non_existant_file HTTP/1.1\r\nHost: localhost\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
@@ -517,154 +401,1257 @@ mod tests {
     }
 
     #[test]
-    fn test_handle_connection_read_body() {
+    fn test_handle_connection_files_nested() {
         let listener = start_local_server();
         let addr = listener.local_addr().unwrap();
 
+        // Create a file in a subdirectory of the directory
+        let dir = "/tmp/http_server_files_nested";
+        fs::create_dir_all(format!("{}/reports/2026", dir)).unwrap();
+        fs::write(format!("{}/reports/2026/q3.csv", dir), "a,b\n").unwrap();
+
         // Run Http Server
         let _ = thread::spawn(move || {
+            let router = create_router(&files_config(dir));
             if let Ok(stream) = listener.accept() {
-                let request = read_request(&stream.0);
-                create_response(&stream.0, request, &Option::Some(String::from("/tmp")));
+                handle_connection(stream.0, &router);
             }
         });
 
-        // Create a test request (Client)
+        // Create test requests (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
-        let request = "POST /files/file_123 HTTP/1.1\r\nHost: localhost\r\n\
-                                        Content-Type: application/octet-stream\r\n\
-                                        Content-Length: 5\r\n\r\n12345";
-        client_stream.write(request.as_bytes()).unwrap();
+        let requests = [
+            (
+                "GET /files/reports/2026/q3.csv HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Type: text/csv; charset=utf-8\r\nX-Content-Type-Options: nosniff\r\nAccept-Ranges: bytes\r\nVary: Accept-Encoding\r\nContent-Length: 4\r\n\r\na,b\n",
+            ),
+            (
+                "GET /files/reports/2025/q3.csv HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                "HTTP/1.1 404 Not Found\r\n\r\n",
+            ),
+            (
+                "POST /files/reports/2025/q3.csv HTTP/1.1\r\nHost: localhost\r\n\
+                    Content-Type: application/octet-stream\r\nContent-Length: 1\r\n\r\nx",
+                "HTTP/1.1 404 Not Found\r\n\r\n",
+            ),
+            (
+                "POST /files/reports/2026/q4.csv HTTP/1.1\r\nHost: localhost\r\n\
+                    Content-Type: application/octet-stream\r\nContent-Length: 1\r\n\r\nx",
+                "HTTP/1.1 201 Created\r\n\r\n",
+            ),
+        ];
+        for (request, expected) in requests {
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            let response = strip_validators(&response[..read_size]);
+            let response_str = String::from_utf8_lossy(&response);
+
+            assert_eq!(response_str, expected, "{}", request);
+        }
+
+        let file_content = fs::read(format!("{}/reports/2026/q4.csv", dir)).unwrap();
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(file_content, b"x");
+    }
+
+    #[test]
+    fn test_handle_connection_files_create_dirs() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        let dir = "/tmp/http_server_files_create_dirs";
+        fs::create_dir_all(dir).unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let config = Config {
+                    create_dirs: true,
+                    ..files_config(dir)
+                };
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&config));
+            }
+        });
+
+        // Create a test request (Client) into a missing subdirectory
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "POST /files/a/b/c.txt HTTP/1.1\r\nHost: localhost\r\n\
+                        Content-Type: application/octet-stream\r\nContent-Length: 3\r\n\r\nabc";
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
         let response_str = String::from_utf8_lossy(&response[..read_size]);
 
+        let file_content = fs::read(format!("{}/a/b/c.txt", dir));
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+
         assert_eq!(response_str, "HTTP/1.1 201 Created\r\n\r\n");
+        assert_eq!(file_content.unwrap(), b"abc");
+    }
 
-        // Check if the file was created
-        let file_content = fs::read("/tmp/file_123").unwrap();
-        assert_eq!(file_content, vec![49, 50, 51, 52, 53]);
+    #[test]
+    fn test_handle_connection_files_delete() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
 
-        // Clean up the file
-        fs::remove_file("/tmp/file_123").unwrap();
+        // Create a file, an empty directory and a non-empty directory
+        let dir = "/tmp/http_server_files_delete";
+        fs::create_dir_all(format!("{}/empty", dir)).unwrap();
+        fs::create_dir_all(format!("{}/full", dir)).unwrap();
+        fs::write(format!("{}/foo", dir), "foo").unwrap();
+        fs::write(format!("{}/full/bar", dir), "bar").unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            let router = create_router(&files_config(dir));
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &router);
+            }
+        });
+
+        // Create test requests (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let requests = [
+            ("DELETE /files/foo", "HTTP/1.1 204 No Content\r\n\r\n"),
+            ("DELETE /files/foo", "HTTP/1.1 404 Not Found\r\n\r\n"),
+            ("DELETE /files/empty", "HTTP/1.1 204 No Content\r\n\r\n"),
+            ("DELETE /files/full", "HTTP/1.1 409 Conflict\r\n\r\n"),
+            (
+                "DELETE /files/..%2Fpasswd",
+                "HTTP/1.1 403 Forbidden\r\n\r\n",
+            ),
+            ("DELETE /files/", "HTTP/1.1 403 Forbidden\r\n\r\n"),
+        ];
+        for (request_line, expected) in requests {
+            let request = format!("{} HTTP/1.1\r\nHost: localhost\r\n\r\n", request_line);
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+            assert_eq!(response_str, expected, "{}", request_line);
+        }
+
+        let remaining = (
+            std::path::Path::new(&format!("{}/foo", dir)).exists(),
+            std::path::Path::new(&format!("{}/empty", dir)).exists(),
+            std::path::Path::new(&format!("{}/full/bar", dir)).exists(),
+        );
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+
+        assert_eq!(remaining, (false, false, true));
     }
 
     #[test]
-    fn test_handle_connection_accept_encoding() {
+    fn test_handle_connection_files_put() {
         let listener = start_local_server();
         let addr = listener.local_addr().unwrap();
 
+        let dir = "/tmp/http_server_files_put";
+        fs::create_dir_all(dir).unwrap();
+
         // Run Http Server
         let _ = thread::spawn(move || {
+            let router = create_router(&files_config(dir));
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                handle_connection(stream.0, &router);
             }
         });
 
-        // Create a test request (Client)
+        // Create test requests (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
-        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
-        // Read the response
-        let mut response = [0; 1024];
-        let read_size = client_stream.read(&mut response).unwrap();
-        let response_str = String::from_utf8_lossy(&response[..read_size]);
+        let requests = [
+            (
+                "If-Match: *\r\n",
+                "a",
+                "HTTP/1.1 412 Precondition Failed\r\n\r\n",
+            ),
+            ("If-None-Match: *\r\n", "b", "HTTP/1.1 201 Created\r\n\r\n"),
+            (
+                "If-None-Match: *\r\n",
+                "c",
+                "HTTP/1.1 412 Precondition Failed\r\n\r\n",
+            ),
+            (
+                "If-Match: \"unknown\"\r\n",
+                "d",
+                "HTTP/1.1 412 Precondition Failed\r\n\r\n",
+            ),
+            ("", "e", "HTTP/1.1 204 No Content\r\n\r\n"),
+        ];
+        for (headers, body, expected) in requests {
+            let request = format!(
+                "PUT /files/foo HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: 1\r\n\r\n{}",
+                headers, body
+            );
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+            assert_eq!(response_str, expected, "{}", headers);
+        }
 
-        assert!(response_str.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\nContent-Length: 23\r\n\r\n"));
+        let file_content = fs::read(format!("{}/foo", dir)).unwrap();
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
 
-        // gzip encoding
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all("abc".as_bytes()).unwrap();
-        let compress_data = encoder.finish().unwrap();
-        // Check if the response ends with the compressed data
-        assert!(response[..read_size].ends_with(&compress_data));
+        assert_eq!(file_content, b"e");
     }
 
     #[test]
-    fn test_handle_connection_multiple_encoding() {
+    fn test_handle_connection_files_range() {
         let listener = start_local_server();
         let addr = listener.local_addr().unwrap();
 
+        // Create a file in the directory
+        let dir = "/tmp/http_server_files_range";
+        fs::create_dir_all(dir).unwrap();
+        fs::write(format!("{}/digits", dir), "0123456789").unwrap();
+
         // Run Http Server
         let _ = thread::spawn(move || {
+            let router = create_router(&files_config(dir));
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                handle_connection(stream.0, &router);
             }
         });
 
-        // Create a test request (Client)
+        // Create test requests (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
-        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: invalid-encoding-1, gzip, invalid-encoding-2\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
-        // Read the response
-        let mut response = [0; 1024];
-        let read_size = client_stream.read(&mut response).unwrap();
-        let response_str = String::from_utf8_lossy(&response[..read_size]);
+        let mut responses = vec![];
+        let requests = [
+            "GET /files/digits HTTP/1.1\r\nHost: localhost\r\nRange: bytes=2-4\r\n\r\n",
+            "GET /files/digits HTTP/1.1\r\nHost: localhost\r\nRange: bytes=0-0,-2\r\n\r\n",
+            "GET /files/digits HTTP/1.1\r\nHost: localhost\r\nRange: bytes=10-\r\n\r\n",
+            "GET /files/digits HTTP/1.1\r\nHost: localhost\r\nRange: bytes=2-4\r\n\
+                If-Range: \"stale\"\r\n\r\n",
+        ];
+        for request in requests {
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            responses.push(
+                String::from_utf8_lossy(&strip_validators(&response[..read_size])).to_string(),
+            );
+        }
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
 
-        assert!(response_str.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Encoding: gzip\r\nContent-Length: 23\r\n\r\n"));
+        assert_eq!(
+            responses[0],
+            "HTTP/1.1 206 Partial Content\r\nContent-Type: application/octet-stream\r\n\
+                X-Content-Type-Options: nosniff\r\nAccept-Ranges: bytes\r\nContent-Range: bytes 2-4/10\r\nContent-Length: 3\r\n\r\n234"
+        );
+        assert!(responses[1].starts_with(
+            "HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary="
+        ));
+        assert!(responses[1].contains(
+            "\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 0-0/10\r\n\r\n0\r\n--"
+        ));
+        assert!(responses[1].contains(
+            "\r\nContent-Type: application/octet-stream\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n--"
+        ));
+        assert!(responses[1].ends_with("--\r\n"));
+        assert_eq!(
+            responses[2],
+            "HTTP/1.1 416 Range Not Satisfiable\r\nAccept-Ranges: bytes\r\nContent-Range: bytes */10\r\n\r\n"
+        );
+        assert_eq!(
+            responses[3],
+            "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\n\
+                X-Content-Type-Options: nosniff\r\nAccept-Ranges: bytes\r\nContent-Length: 10\r\n\r\n0123456789"
+        );
+    }
 
-        // gzip encoding
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all("abc".as_bytes()).unwrap();
-        let compress_data = encoder.finish().unwrap();
-        // Check if the response ends with the compressed data
-        assert!(response[..read_size].ends_with(&compress_data));
+    #[test]
+    fn test_handle_connection_files_not_modified() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Create a file in the directory
+        let dir = "/tmp/http_server_files_not_modified";
+        fs::create_dir_all(dir).unwrap();
+        fs::write(format!("{}/artifact", dir), "abc").unwrap();
+
+        // Run Http Server with weak entity tags
+        let _ = thread::spawn(move || {
+            let config = Config {
+                weak_etags: true,
+                ..files_config(dir)
+            };
+            let router = create_router(&config);
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &router);
+            }
+        });
+
+        // Create test requests (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let mut send = |request: &str| {
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            String::from_utf8_lossy(&response[..read_size]).to_string()
+        };
+        let response = send("GET /files/artifact HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        let header = |name: &str| {
+            response
+                .split("\r\n")
+                .find_map(|line| line.strip_prefix(name))
+                .unwrap()
+                .to_string()
+        };
+        let etag = header("ETag: ");
+        let last_modified = header("Last-Modified: ");
+        let responses = [
+            send(&format!(
+                "GET /files/artifact HTTP/1.1\r\nHost: localhost\r\nIf-None-Match: {}\r\n\r\n",
+                etag
+            )),
+            send(&format!(
+                "GET /files/artifact HTTP/1.1\r\nHost: localhost\r\nIf-Modified-Since: {}\r\n\r\n",
+                last_modified
+            )),
+            send("GET /files/artifact HTTP/1.1\r\nHost: localhost\r\nIf-None-Match: \"x\"\r\n\r\n"),
+        ];
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+
+        assert!(etag.starts_with("W/\""));
+        let not_modified = format!(
+            "HTTP/1.1 304 Not Modified\r\nETag: {}\r\nLast-Modified: {}\r\n\r\n",
+            etag, last_modified
+        );
+        assert_eq!(responses[0], not_modified);
+        assert_eq!(responses[1], not_modified);
+        assert!(responses[2].starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(responses[2].ends_with("\r\n\r\nabc"));
     }
 
     #[test]
-    fn test_handle_connection_invalid_encoding() {
+    fn test_handle_connection_files_content_type() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Create files with and without a known extension and a mime.types file
+        let dir = "/tmp/http_server_files_content_type";
+        fs::create_dir_all(dir).unwrap();
+        fs::write(format!("{}/index.html", dir), "<p>hi</p>").unwrap();
+        fs::write(format!("{}/main.rs", dir), "fn main() {}").unwrap();
+        fs::write(format!("{}/README", dir), "read me").unwrap();
+        fs::write(format!("{}/mime.types", dir), "text/x-rust rs\n").unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            let config = Config {
+                mime_types: Some(format!("{}/mime.types", dir).into()),
+                sniff: true,
+                ..files_config(dir)
+            };
+            let router = create_router(&config);
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &router);
+            }
+        });
+
+        // Create test requests (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let requests = [
+            ("index.html", "text/html; charset=utf-8"),
+            ("main.rs", "text/x-rust; charset=utf-8"),
+            ("README", "text/plain; charset=utf-8"),
+        ];
+        for (name, content_type) in requests {
+            let request = format!("GET /files/{} HTTP/1.1\r\nHost: localhost\r\n\r\n", name);
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            let response = strip_validators(&response[..read_size]);
+            let response_str = String::from_utf8_lossy(&response);
+
+            let expected = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nX-Content-Type-Options: nosniff\r\n",
+                content_type
+            );
+            assert!(response_str.starts_with(&expected), "{}", response_str);
+        }
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_handle_connection_files_compressed() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Create a text file larger than the stream buffer and a small one
+        let dir = "/tmp/http_server_files_compressed";
+        fs::create_dir_all(dir).unwrap();
+        let content = "All work and no play makes Jack a dull boy.\n".repeat(5000);
+        fs::write(format!("{}/large.txt", dir), &content).unwrap();
+        fs::write(format!("{}/small.txt", dir), "small").unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            let mut config = files_config(dir);
+            config.compression.min_size = 100;
+            let router = create_router(&config);
+            for stream in listener.incoming().take(2) {
+                handle_connection(stream.unwrap(), &router);
+            }
+        });
+
+        // Create test requests (Client)
+        let get = |name: &str| {
+            let mut client_stream = TcpStream::connect(addr).unwrap();
+            let request = format!(
+                "GET /files/{} HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\nConnection: close\r\n\r\n",
+                name
+            );
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = Vec::new();
+            client_stream.read_to_end(&mut response).unwrap();
+            response
+        };
+        let large = get("large.txt");
+        let small = get("small.txt");
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+
+        // Small files are not compressed
+        let small = String::from_utf8_lossy(&small);
+        assert!(!small.contains("Content-Encoding"));
+        assert!(small.ends_with("\r\n\r\nsmall"));
+
+        // Large files are compressed while they are streamed with chunked encoding
+        let end = large.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&large[..end]);
+        assert!(head.contains("\r\nETag: W/\""));
+        assert!(head.contains("\r\nVary: Accept-Encoding\r\nContent-Encoding: gzip\r\n"));
+        assert!(head.contains("\r\nTransfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+        let mut reader = &large[end..];
+        let mut body = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let size = usize::from_str_radix(line.trim_end(), 16).unwrap();
+            let mut chunk = vec![0; size + 2];
+            reader.read_exact(&mut chunk).unwrap();
+            if size == 0 {
+                break;
+            }
+            body.extend_from_slice(&chunk[..size]);
+        }
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&body[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+    }
+
+    #[test]
+    fn test_handle_connection_files_listing() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Create a directory with a file and a subdirectory
+        let dir = "/tmp/http_server_files_listing";
+        fs::create_dir_all(format!("{}/sub", dir)).unwrap();
+        fs::write(format!("{}/a b.txt", dir), "abc").unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            let router = create_router(&files_config(dir));
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &router);
+            }
+        });
+
+        // Create test requests (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let mut responses = vec![];
+        let requests = [
+            "GET /files/ HTTP/1.1\r\nHost: localhost\r\nAccept: application/json\r\n\r\n",
+            "GET /files HTTP/1.1\r\nHost: localhost\r\nAccept: text/html\r\n\r\n",
+            "GET /files/sub HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        ];
+        for request in requests {
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            responses.push(String::from_utf8_lossy(&response[..read_size]).to_string());
+        }
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
+
+        assert!(responses[0]
+            .starts_with("HTTP/1.1 200 OK\r\nVary: Accept\r\nContent-Type: application/json\r\n"));
+        assert!(responses[0].contains(
+            "\r\n\r\n{\"path\":\"/files/\",\"entries\":[{\"name\":\"a b.txt\",\"type\":\"file\",\"size\":3,"
+        ));
+        assert!(responses[0].contains("{\"name\":\"sub\",\"type\":\"directory\","));
+        assert!(responses[1].starts_with(
+            "HTTP/1.1 200 OK\r\nVary: Accept\r\nContent-Type: text/html; charset=utf-8\r\n"
+        ));
+        assert!(responses[1].contains("<a href=\"/files/a%20b.txt\">a b.txt</a>"));
+        assert!(responses[1].contains("<a href=\"/files/sub/\">sub/</a>"));
+        assert!(responses[2].contains("<h1>Index of /files/sub/</h1>"));
+    }
+
+    #[test]
+    fn test_handle_connection_files_no_listing() {
         let listener = start_local_server();
         let addr = listener.local_addr().unwrap();
 
         // Run Http Server
         let _ = thread::spawn(move || {
+            let config = Config {
+                no_listing: true,
+                ..files_config("/tmp")
+            };
             if let Ok(stream) = listener.accept() {
-                handle_connection(stream.0);
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&config));
             }
         });
 
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
-        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: invalid-encoding-1, invalid-encoding-2\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        let request = "GET /files/ HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
         let response_str = String::from_utf8_lossy(&response[..read_size]);
 
-        assert_eq!(
-            response_str,
-            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 3\r\n\r\nabc"
-        );
+        assert_eq!(response_str, "HTTP/1.1 404 Not Found\r\n\r\n");
     }
 
     #[test]
-    fn test_handle_connection_persistent() {
+    fn test_handle_connection_files_traversal() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            let router = create_router(&files_config("/tmp"));
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &router);
+            }
+        });
+
+        // Create test requests (Client) that try to escape the directory
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let requests = [
+            "GET /files/..%2F..%2Fetc%2Fpasswd HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "GET /files/.. HTTP/1.1\r\nHost: localhost\r\n\r\n",
+            "POST /files/..%2Fx HTTP/1.1\r\nHost: localhost\r\n\
+                Content-Type: application/octet-stream\r\nContent-Length: 1\r\n\r\nx",
+        ];
+        for request in requests {
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+            assert_eq!(
+                response_str, "HTTP/1.1 403 Forbidden\r\n\r\n",
+                "{}",
+                request
+            );
+        }
+        assert!(!std::path::Path::new("/x").exists());
+    }
+
+    #[test]
+    fn test_handle_connection_files_symlink() {
         let listener = start_local_server();
         let addr = listener.local_addr().unwrap();
 
+        // Create a symlink that points outside the directory
+        let dir = "/tmp/http_server_files_symlink";
+        fs::create_dir_all(dir).unwrap();
+        let _ = fs::remove_file(format!("{}/passwd", dir));
+        std::os::unix::fs::symlink("/etc/passwd", format!("{}/passwd", dir)).unwrap();
+
         // Run Http Server
         let _ = thread::spawn(move || {
             if let Ok(stream) = listener.accept() {
-                let request = read_request(&stream.0);
-                assert!(create_response(&stream.0, request, &Option::None));
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config(dir)));
             }
         });
 
         // Create a test request (Client)
         let mut client_stream = TcpStream::connect(addr).unwrap();
-        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
-        client_stream.write(request.as_bytes()).unwrap();
+        let request = "GET /files/passwd HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
         // Read the response
         let mut response = [0; 1024];
         let read_size = client_stream.read(&mut response).unwrap();
         let response_str = String::from_utf8_lossy(&response[..read_size]);
+        // Clean up the directory
+        fs::remove_dir_all(dir).unwrap();
 
-        assert_eq!(
-            response_str,
-            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 3\r\nConnection: close\r\n\r\nabc"
-        );
+        assert_eq!(response_str, "HTTP/1.1 403 Forbidden\r\n\r\n");
+    }
+
+    #[test]
+    fn test_handle_connection_read_body() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "POST /files/file_123 HTTP/1.1\r\nHost: localhost\r\n\
+                                        Content-Type: application/octet-stream\r\n\
+                                        Content-Length: 5\r\n\r\n12345";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(response_str, "HTTP/1.1 201 Created\r\n\r\n");
+
+        // Check if the file was created
+        let file_content = fs::read("/tmp/file_123").unwrap();
+        assert_eq!(file_content, vec![49, 50, 51, 52, 53]);
+
+        // Clean up the file
+        fs::remove_file("/tmp/file_123").unwrap();
+    }
+
+    #[test]
+    fn test_handle_connection_binary_body() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
+            }
+        });
+
+        // Create a test request (Client) with a body that is not valid UTF-8
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let body: Vec<u8> = vec![
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff, 0xfe,
+        ];
+        let mut request = format!(
+            "POST /files/file_binary HTTP/1.1\r\nHost: localhost\r\n\
+                                        Content-Type: application/octet-stream\r\n\
+                                        Content-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&body);
+        client_stream.write_all(&request).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(response_str, "HTTP/1.1 201 Created\r\n\r\n");
+
+        // Check if the file has the same bytes as the body
+        let file_content = fs::read("/tmp/file_binary").unwrap();
+        assert_eq!(file_content, body);
+
+        // Clean up the file
+        fs::remove_file("/tmp/file_binary").unwrap();
+    }
+
+    #[test]
+    fn test_handle_connection_encoded_body() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            let router = create_router(&files_config("/tmp"));
+            for stream in listener.incoming().take(2) {
+                handle_connection(stream.unwrap(), &router);
+            }
+        });
+
+        // Create a test request (Client) with a gzip-compressed body
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"uncompressed content").unwrap();
+        let body = encoder.finish().unwrap();
+        let mut request = format!(
+            "POST /files/file_encoded HTTP/1.1\r\nHost: localhost\r\n\
+                Content-Type: application/octet-stream\r\nContent-Encoding: gzip\r\n\
+                Content-Length: {}\r\n\r\n",
+            body.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(&body);
+        client_stream.write_all(&request).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(response_str, "HTTP/1.1 201 Created\r\n\r\n");
+
+        // Check if the file has the decoded body
+        let file_content = fs::read("/tmp/file_encoded").unwrap();
+        fs::remove_file("/tmp/file_encoded").unwrap();
+        assert_eq!(file_content, b"uncompressed content");
+
+        // Create a test request (Client) with an unsupported coding
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "POST /files/file_encoded HTTP/1.1\r\nHost: localhost\r\n\
+            Content-Type: application/octet-stream\r\nContent-Encoding: compress\r\n\
+            Content-Length: 3\r\n\r\nabc";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(
+            response_str,
+            "HTTP/1.1 415 Unsupported Media Type\r\nConnection: close\r\n\r\n"
+        );
+        assert!(!std::path::Path::new("/tmp/file_encoded").exists());
+    }
+
+    #[test]
+    fn test_handle_connection_chunked_body() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
+            }
+        });
+
+        // Create a test request (Client) with a chunked body
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "POST /files/file_chunked HTTP/1.1\r\nHost: localhost\r\n\
+                                        Content-Type: application/octet-stream\r\n\
+                                        Transfer-Encoding: chunked\r\n\r\n\
+                                        5;ext=1\r\nHello\r\n8\r\n, World!\r\n0\r\nX-Trailer: x\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(response_str, "HTTP/1.1 201 Created\r\n\r\n");
+
+        // Check if the decoded body was written to the file
+        let file_content = fs::read("/tmp/file_chunked").unwrap();
+        assert_eq!(file_content, b"Hello, World!");
+
+        // Clean up the file
+        fs::remove_file("/tmp/file_chunked").unwrap();
+    }
+
+    #[test]
+    fn test_handle_connection_large_body() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                create_response(&stream.0, request, &create_router(&files_config("/tmp")));
+            }
+        });
+
+        // Create a test request (Client) bigger than a single read
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let body = "0123456789".repeat(500);
+        let request = format!(
+            "POST /files/file_large HTTP/1.1\r\nHost: localhost\r\n\
+                                        Content-Type: application/octet-stream\r\n\
+                                        Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(response_str, "HTTP/1.1 201 Created\r\n\r\n");
+
+        // Check if the whole body was written to the file
+        let file_content = fs::read("/tmp/file_large").unwrap();
+        assert_eq!(file_content, body.as_bytes());
+
+        // Clean up the file
+        fs::remove_file("/tmp/file_large").unwrap();
+    }
+
+    #[test]
+    fn test_handle_connection_accept_encoding() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert!(response_str.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Encoding: gzip\r\nContent-Length: 23\r\n\r\n"));
+
+        // gzip encoding
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all("abc".as_bytes()).unwrap();
+        let compress_data = encoder.finish().unwrap();
+        // Check if the response ends with the compressed data
+        assert!(response[..read_size].ends_with(&compress_data));
+    }
+
+    #[test]
+    #[cfg(feature = "deflate")]
+    fn test_handle_connection_accept_encoding_deflate() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request =
+            "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: deflate\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        let (head, _) = response_str.split_once("\r\n\r\n").unwrap();
+        let body = &response[head.len() + 4..read_size];
+        assert_eq!(
+            head,
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Encoding: deflate\r\nContent-Length: {}",
+                body.len()
+            )
+        );
+
+        // deflate decoding
+        let mut decoded = String::new();
+        flate2::read::ZlibDecoder::new(body)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "abc");
+    }
+
+    #[test]
+    #[cfg(feature = "br")]
+    fn test_handle_connection_accept_encoding_br() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: br\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        let (head, _) = response_str.split_once("\r\n\r\n").unwrap();
+        let body = &response[head.len() + 4..read_size];
+        assert_eq!(
+            head,
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Encoding: br\r\nContent-Length: {}",
+                body.len()
+            )
+        );
+
+        // br decoding
+        let mut decoded = String::new();
+        brotli::Decompressor::new(body, 4096)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "abc");
+    }
+
+    #[test]
+    #[cfg(feature = "zstd")]
+    fn test_handle_connection_accept_encoding_zstd() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: zstd\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        let (head, _) = response_str.split_once("\r\n\r\n").unwrap();
+        let body = &response[head.len() + 4..read_size];
+        assert_eq!(
+            head,
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Encoding: zstd\r\nContent-Length: {}",
+                body.len()
+            )
+        );
+
+        // zstd decoding
+        let mut decoded = String::new();
+        zstd::stream::read::Decoder::new(body)
+            .unwrap()
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "abc");
+    }
+
+    #[test]
+    fn test_handle_connection_multiple_encoding() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: invalid-encoding-1, gzip, invalid-encoding-2\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert!(response_str.starts_with("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Encoding: gzip\r\nContent-Length: 23\r\n\r\n"));
+
+        // gzip encoding
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all("abc".as_bytes()).unwrap();
+        let compress_data = encoder.finish().unwrap();
+        // Check if the response ends with the compressed data
+        assert!(response[..read_size].ends_with(&compress_data));
+    }
+
+    #[test]
+    fn test_handle_connection_lowercase_header() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client) with lowercase header names
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nhost: localhost\r\naccept-encoding: gzip\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert!(response_str.starts_with(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Encoding: gzip\r\n"
+        ));
+    }
+
+    #[test]
+    fn test_handle_connection_invalid_encoding() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: invalid-encoding-1, invalid-encoding-2\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(
+            response_str,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 3\r\n\r\nabc"
+        );
+    }
+
+    #[test]
+    fn test_handle_connection_encoding_q_values() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create test requests (Client) that refuse gzip
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let requests = [
+            (
+                "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip;q=0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 3\r\n\r\nabc",
+            ),
+            (
+                "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: gzip; q=0.5, identity\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 3\r\n\r\nabc",
+            ),
+            (
+                "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: compress, identity;q=0\r\n\r\n",
+                "HTTP/1.1 406 Not Acceptable\r\nVary: Accept-Encoding\r\n\r\n",
+            ),
+        ];
+        for (request, expected) in requests {
+            client_stream.write_all(request.as_bytes()).unwrap();
+            // Read the response
+            let mut response = [0; 1024];
+            let read_size = client_stream.read(&mut response).unwrap();
+            let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+            assert_eq!(response_str, expected, "{}", request);
+        }
+    }
+
+    #[test]
+    fn test_handle_connection_persistent() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                let mut reader = RequestReader::new(&stream.0);
+                let request = read_request(&mut reader).unwrap().unwrap();
+                assert!(create_response(
+                    &stream.0,
+                    request,
+                    &create_router(&Config::default())
+                ));
+            }
+        });
+
+        // Create a test request (Client)
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(
+            response_str,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 3\r\nConnection: close\r\n\r\nabc"
+        );
+    }
+
+    #[test]
+    fn test_handle_connection_unsupported_method() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client) with a method no route implements
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "PUT /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(response_str, "HTTP/1.1 501 Not Implemented\r\n\r\n");
+
+        // The connection is still alive after the unsupported method
+        let request = "GET /echo/abc HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(
+            response_str,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nVary: Accept-Encoding\r\nContent-Length: 3\r\n\r\nabc"
+        );
+    }
+
+    #[test]
+    fn test_handle_connection_bad_request() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client) with a header without a colon
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = "GET / HTTP/1.1\r\nHost localhost\r\n\r\n";
+        client_stream.write_all(request.as_bytes()).unwrap();
+        // Read the response until the server closes the connection
+        let mut response = Vec::new();
+        client_stream.read_to_end(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response);
+
+        assert_eq!(
+            response_str,
+            "HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn test_handle_connection_header_section_too_large() {
+        let listener = start_local_server();
+        let addr = listener.local_addr().unwrap();
+
+        // Run Http Server
+        let _ = thread::spawn(move || {
+            if let Ok(stream) = listener.accept() {
+                handle_connection(stream.0, &create_router(&Config::default()));
+            }
+        });
+
+        // Create a test request (Client) with a huge header
+        let mut client_stream = TcpStream::connect(addr).unwrap();
+        let request = format!(
+            "GET / HTTP/1.1\r\nHost: localhost\r\nX-Large: {}\r\n\r\n",
+            "a".repeat(20 * 1024)
+        );
+        // The server may close the connection before the whole request is sent
+        let _ = client_stream.write_all(request.as_bytes());
+        // Read the response
+        let mut response = [0; 1024];
+        let read_size = client_stream.read(&mut response).unwrap();
+        let response_str = String::from_utf8_lossy(&response[..read_size]);
+
+        assert_eq!(
+            response_str,
+            "HTTP/1.1 431 Request Header Fields Too Large\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    fn files_config(dir: &str) -> Config {
+        Config {
+            directory: Some(dir.into()),
+            ..Config::default()
+        }
+    }
+
+    /// Remove the ETag and Last-Modified headers, which depend on the file metadata
+    fn strip_validators(response: &[u8]) -> Vec<u8> {
+        let Some(end) = response.windows(4).position(|window| window == b"\r\n\r\n") else {
+            return response.to_vec();
+        };
+        let mut stripped = Vec::new();
+        for line in response[..end].split(|&byte| byte == b'\n') {
+            if !line.starts_with(b"ETag: ") && !line.starts_with(b"Last-Modified: ") {
+                stripped.extend_from_slice(line);
+                stripped.push(b'\n');
+            }
+        }
+        stripped.pop();
+        stripped.extend_from_slice(&response[end..]);
+        stripped
     }
 
     fn start_local_server() -> TcpListener {
@@ -4,6 +4,7 @@ use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
 pub struct ThreadPool {
+    #[allow(dead_code)]
     workers: Vec<Worker>,
     sender: mpsc::Sender<Job>,
 }
@@ -59,6 +60,7 @@ impl ThreadPool {
 
 /// Struct that represents a worker thread
 /// Each worker will receive jobs from the thread pool
+#[allow(dead_code)]
 struct Worker {
     id: usize,
     thread: thread::JoinHandle<()>,